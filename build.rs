@@ -1,7 +1,37 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
 fn main() {
   if cfg!(target_os = "windows") {
     let mut res = winres::WindowsResource::new();
     res.set_icon("res/icon.ico");
     let _ = res.compile();
   }
+
+  pack_assets();
+}
+
+// Embeds every file in `res/` into the binary so the game doesn't depend on the working directory.
+fn pack_assets() {
+  println!("cargo:rerun-if-changed=res");
+
+  let res_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("res");
+  let mut entries = fs::read_dir(&res_dir)
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.is_file())
+    .collect::<Vec<_>>();
+  entries.sort();
+
+  let mut pack = String::from("pub static ASSETS: &[(&str, &[u8])] = &[\n");
+  for path in entries {
+    let name = path.file_name().unwrap().to_str().unwrap();
+    println!("cargo:rerun-if-changed={}", path.display());
+    writeln!(pack, "  ({:?}, include_bytes!({:?})),", name, path.display().to_string()).unwrap();
+  }
+  pack.push_str("];\n");
+
+  fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("assets.rs"), pack).unwrap();
 }
@@ -0,0 +1,92 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::atlas::{
+  Animation,
+  SpriteAtlas,
+};
+use crate::time::TimeScale;
+use crate::TextureManager;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Clip {
+  Idle,
+  Walk,
+  Bounced,
+  Alert,
+  Grab,
+}
+
+impl Clip {
+  fn name(self) -> &'static str {
+    match self {
+      Clip::Idle => "idle",
+      Clip::Walk => "walk",
+      Clip::Bounced => "bounced",
+      Clip::Alert => "alert",
+      Clip::Grab => "grab",
+    }
+  }
+}
+
+// Plays the `<sprite>.<clip>` animations from `sprites.ron`, falling back to `<sprite>.idle` for
+// clips the sprite doesn't have.
+#[derive(Component, Clone)]
+pub struct Animator {
+  sprite: &'static str,
+  clip: Clip,
+  frame: usize,
+  timer: f32,
+  pub speed: f32,
+  pub color: Color,
+}
+
+impl Animator {
+  pub fn new(sprite: &'static str) -> Animator {
+    Animator { sprite, clip: Clip::Idle, frame: 0, timer: 0.0, speed: 1.0, color: WHITE }
+  }
+
+  pub fn play(&mut self, clip: Clip) {
+    if self.clip != clip {
+      self.clip = clip;
+      self.frame = 0;
+      self.timer = 0.0;
+    }
+  }
+
+  fn animation<'a>(&self, atlas: &'a SpriteAtlas) -> &'a Animation {
+    atlas
+      .animation(&format!("{}.{}", self.sprite, self.clip.name()))
+      .or_else(|| atlas.animation(&format!("{}.idle", self.sprite)))
+      .unwrap_or_else(|| panic!("Sprite `{}` has no idle animation", self.sprite))
+  }
+
+  pub fn draw(&self, atlas: &SpriteAtlas, x: f32, y: f32, params: DrawTextureParams) {
+    let animation = self.animation(atlas);
+    atlas.draw(
+      &animation.frames[self.frame.min(animation.frames.len() - 1)],
+      x,
+      y,
+      self.color,
+      params,
+    );
+  }
+}
+
+pub fn animate(
+  tm: Res<TextureManager>,
+  time_scale: Res<TimeScale>,
+  mut animators: Query<&mut Animator>,
+) {
+  for mut animator in &mut animators {
+    let animation = animator.animation(&tm.atlas);
+
+    let frame_time = 1.0 / animation.fps;
+    animator.timer += time_scale.delta * animator.speed;
+    // The time left over carries into the next frame, a slow frame rate can skip several.
+    while animator.timer >= frame_time {
+      animator.timer -= frame_time;
+      animator.frame = animation.next_frame(animator.frame);
+    }
+  }
+}
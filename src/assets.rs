@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Cursor;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use macroquad::audio::{
+  load_sound_from_bytes,
+  Sound,
+};
+use macroquad::file::FileError;
+use macroquad::miniquad::fs::Error;
+use macroquad::prelude::*;
+
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+// Files in this directory take priority over the embedded ones, so assets can be modded without
+// rebuilding the game.
+#[cfg(not(target_arch = "wasm32"))]
+const OVERRIDE_DIR_VAR: &str = "CGYT_ASSETS_DIR";
+#[cfg(not(target_arch = "wasm32"))]
+const OVERRIDE_DIR: &str = "mods";
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn override_dir() -> Option<PathBuf> {
+  if let Some(dir) = std::env::var_os(OVERRIDE_DIR_VAR) {
+    return Some(dir.into());
+  }
+
+  if cfg!(feature = "hot-reload") {
+    // Development builds read straight from the repository so edits show up without copying files.
+    Some(concat!(env!("CARGO_MANIFEST_DIR"), "/res").into())
+  } else {
+    Some(std::env::current_exe().ok()?.parent()?.join(OVERRIDE_DIR))
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn override_path(name: &str) -> Option<PathBuf> {
+  override_dir().map(|dir| dir.join(name)).filter(|path| path.is_file())
+}
+
+pub fn load_file(name: &str) -> Result<Cow<'static, [u8]>, FileError> {
+  #[cfg(not(target_arch = "wasm32"))]
+  if let Some(path) = override_path(name) {
+    return std::fs::read(path)
+      .map(Cow::Owned)
+      .map_err(|err| FileError::new(Error::IOError(err), name));
+  }
+
+  load_embedded(name).map(Cow::Borrowed)
+}
+
+// The copy of the file built into the game, whatever the override directory has.
+pub fn load_embedded(name: &str) -> Result<&'static [u8], FileError> {
+  ASSETS
+    .iter()
+    .find(|(asset, _)| *asset == name)
+    .map(|(_, bytes)| *bytes)
+    .ok_or_else(|| FileError::new(Error::IOError(std::io::ErrorKind::NotFound.into()), name))
+}
+
+// Reads a file with `decode`. A modded file that can't be read or decoded gets replaced by the
+// embedded one, so a broken mod doesn't keep the game from starting.
+pub fn load_with<T>(name: &str, decode: impl Fn(&[u8]) -> Result<T, String>) -> Result<T, String> {
+  #[cfg(not(target_arch = "wasm32"))]
+  if let Some(path) = override_path(name) {
+    match std::fs::read(&path).map_err(|err| err.to_string()).and_then(|bytes| decode(&bytes)) {
+      Ok(value) => return Ok(value),
+      Err(err) => warn!("Failed to load {}, using the built-in one: {}", path.display(), err),
+    }
+  }
+
+  decode(load_embedded(name).map_err(|err| err.to_string())?)
+}
+
+// Every file there is, embedded or in the override directory.
+pub fn names() -> Vec<String> {
+  let embedded = ASSETS.iter().map(|(name, _)| name.to_string());
+  #[cfg(not(target_arch = "wasm32"))]
+  if let Some(entries) = override_dir().and_then(|dir| std::fs::read_dir(dir).ok()) {
+    let overrides = entries.filter_map(|entry| entry.ok()?.file_name().into_string().ok());
+    return embedded.chain(overrides).collect();
+  }
+  embedded.collect()
+}
+
+// The audio backend panics on sounds it can't decode, so they get decoded once here first.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_sound(bytes: &[u8]) -> Result<(), String> {
+  let mut reader = audrey::Reader::new(Cursor::new(bytes)).map_err(|err| err.to_string())?;
+  if !matches!(reader.description().channel_count(), 1 | 2) {
+    return Err("only mono and stereo sounds can be played".to_string());
+  }
+  reader.samples::<f32>().try_for_each(|sample| sample.map(|_| ())).map_err(|err| err.to_string())
+}
+
+// The browser decodes sounds itself and reports what it can't.
+#[cfg(target_arch = "wasm32")]
+fn check_sound(_bytes: &[u8]) -> Result<(), String> { Ok(()) }
+
+pub async fn load_sound(name: &str) -> Result<Sound, String> {
+  let bytes = load_with(name, |bytes| check_sound(bytes).map(|()| bytes.to_vec()))?;
+  load_sound_from_bytes(&bytes).await.map_err(|err| err.to_string())
+}
+
+pub async fn load_ttf_font(name: &str) -> Result<Font, String> {
+  load_with(name, |bytes| load_ttf_font_from_bytes(bytes).map_err(|err| err.to_string()))
+}
+
+// Decodes an image without the panic macroquad gives one it can't read.
+pub fn decode_image(bytes: &[u8]) -> Result<Image, String> {
+  let image = image::load_from_memory(bytes).map_err(|err| err.to_string())?.to_rgba8();
+  let (Ok(width), Ok(height)) = (u16::try_from(image.width()), u16::try_from(image.height()))
+  else {
+    return Err("the image is too large".to_string());
+  };
+  Ok(Image { width, height, bytes: image.into_raw() })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A broken mod has to be caught by these instead of panicking in macroquad.
+  #[test]
+  fn broken_files_are_rejected() {
+    assert!(decode_image(b"not a png").is_err());
+    assert!(decode_image(&load_embedded("cgyt.png").unwrap()[..100]).is_err());
+    assert!(check_sound(b"not a wav").is_err());
+    assert!(check_sound(&load_embedded("ui.wav").unwrap()[..100]).is_err());
+  }
+
+  #[test]
+  fn embedded_files_decode() {
+    assert!(decode_image(load_embedded("cgyt.png").unwrap()).is_ok());
+    assert!(check_sound(load_embedded("ui.wav").unwrap()).is_ok());
+  }
+}
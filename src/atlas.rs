@@ -0,0 +1,243 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use macroquad::file::FileError;
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::assets;
+
+const MANIFEST: &str = "sprites.ron";
+const ATLAS_WIDTH: usize = 1024;
+// Empty space between sprites so neighbours don't bleed into each other when scaled.
+const ATLAS_PADDING: usize = 1;
+
+// What the game draws by name, checked when loading so a bad edit of the manifest fails there
+// instead of in the middle of a level.
+const REQUIRED_SPRITES: &[&str] = &[
+  "bouncier_powerup",
+  "cat_black",
+  "cat_grey",
+  "cgyt",
+  "cobblestone",
+  "no_bounce_powerup",
+  "speed_up_powerup",
+];
+const REQUIRED_ANIMATIONS: &[&str] = &[
+  "cat_black.idle",
+  "cat_grey.idle",
+  "cat_herder.idle",
+  "cat_orange.idle",
+  "cat_pouncer.idle",
+  "cat_sneak.idle",
+  "cat_splitter.idle",
+  "manekineko.idle",
+  "skull.idle",
+  "tongue.idle",
+];
+
+#[derive(Deserialize)]
+struct Manifest {
+  sprites: HashMap<String, SpriteDef>,
+  #[serde(default)]
+  animations: HashMap<String, Animation>,
+}
+
+#[derive(Deserialize)]
+struct SpriteDef {
+  file: String,
+  #[serde(default)]
+  region: Option<(u16, u16, u16, u16)>,
+  // Multiplies the colors of the image, for palette swapped variants of a sprite.
+  #[serde(default)]
+  tint: Option<(u8, u8, u8)>,
+}
+
+#[derive(Deserialize)]
+pub struct Animation {
+  pub frames: Vec<String>,
+  pub fps: f32,
+  #[serde(default)]
+  pub looping: bool,
+}
+
+impl Animation {
+  pub fn next_frame(&self, frame: usize) -> usize {
+    if self.looping {
+      (frame + 1) % self.frames.len()
+    } else {
+      (frame + 1).min(self.frames.len() - 1)
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+  File(FileError),
+  // An image that can't be decoded, with the reason.
+  Image(String, String),
+  Manifest(ron::error::SpannedError),
+  UnknownSprite(String),
+  MissingAnimation(String),
+  // No frames, or a frame rate that isn't positive.
+  InvalidAnimation(String),
+  // A region that is empty or reaches outside of its image.
+  InvalidRegion(String),
+  TooWide(String),
+  // The packed sprites don't fit into the largest texture that can be made.
+  TooTall,
+}
+
+impl fmt::Display for AtlasError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AtlasError::File(err) => write!(f, "{}", err),
+      AtlasError::Image(file, err) => write!(f, "{}: {}", file, err),
+      AtlasError::Manifest(err) => write!(f, "{}: {}", MANIFEST, err),
+      AtlasError::UnknownSprite(id) => write!(f, "{}: unknown sprite `{}`", MANIFEST, id),
+      AtlasError::MissingAnimation(id) => write!(f, "{}: missing animation `{}`", MANIFEST, id),
+      AtlasError::InvalidAnimation(id) => {
+        write!(f, "{}: animation `{}` needs frames and a positive fps", MANIFEST, id)
+      },
+      AtlasError::InvalidRegion(id) => {
+        write!(f, "{}: the region of `{}` is empty or outside of its image", MANIFEST, id)
+      },
+      AtlasError::TooWide(id) => {
+        write!(f, "{}: `{}` is wider than the atlas ({}px)", MANIFEST, id, ATLAS_WIDTH)
+      },
+      AtlasError::TooTall => write!(f, "{}: the sprites don't fit into one texture", MANIFEST),
+    }
+  }
+}
+
+// All the sprites of the game packed into one texture, so drawing them doesn't switch textures.
+pub struct SpriteAtlas {
+  texture: Texture2D,
+  regions: HashMap<String, Rect>,
+  animations: HashMap<String, Animation>,
+}
+
+impl SpriteAtlas {
+  // The manifest and the images have to fit together, so a modded atlas that can't be built gets
+  // replaced by the embedded one as a whole.
+  pub fn load() -> Result<SpriteAtlas, AtlasError> {
+    SpriteAtlas::build(assets::load_file).or_else(|err| {
+      warn!("Failed to load the modded sprites, using the built-in ones: {}", err);
+      SpriteAtlas::build(|name| assets::load_embedded(name).map(Cow::Borrowed))
+    })
+  }
+
+  fn build(
+    load_file: impl Fn(&str) -> Result<Cow<'static, [u8]>, FileError>,
+  ) -> Result<SpriteAtlas, AtlasError> {
+    let manifest = load_file(MANIFEST).map_err(AtlasError::File)?;
+    let manifest: Manifest = ron::de::from_bytes(&manifest).map_err(AtlasError::Manifest)?;
+
+    for (id, animation) in &manifest.animations {
+      if animation.frames.is_empty() || animation.fps.is_nan() || animation.fps <= 0.0 {
+        return Err(AtlasError::InvalidAnimation(id.clone()));
+      }
+      if let Some(id) = animation.frames.iter().find(|id| !manifest.sprites.contains_key(*id)) {
+        return Err(AtlasError::UnknownSprite(id.clone()));
+      }
+    }
+    if let Some(id) = REQUIRED_SPRITES.iter().find(|id| !manifest.sprites.contains_key(**id)) {
+      return Err(AtlasError::UnknownSprite(id.to_string()));
+    }
+    if let Some(id) = REQUIRED_ANIMATIONS.iter().find(|id| !manifest.animations.contains_key(**id))
+    {
+      return Err(AtlasError::MissingAnimation(id.to_string()));
+    }
+
+    let mut images = HashMap::new();
+    let mut sprites = Vec::new();
+    for (id, def) in manifest.sprites {
+      if !images.contains_key(&def.file) {
+        let bytes = load_file(&def.file).map_err(AtlasError::File)?;
+        let image =
+          assets::decode_image(&bytes).map_err(|err| AtlasError::Image(def.file.clone(), err))?;
+        images.insert(def.file.clone(), image);
+      }
+
+      let image = &images[&def.file];
+      let (x, y, w, h) = def.region.unwrap_or((0, 0, image.width, image.height));
+      let inside =
+        x as usize + w as usize <= image.width() && y as usize + h as usize <= image.height();
+      if w == 0 || h == 0 || !inside {
+        return Err(AtlasError::InvalidRegion(id));
+      }
+      if w as usize > ATLAS_WIDTH {
+        return Err(AtlasError::TooWide(id));
+      }
+      let mut sprite = image.sub_image(Rect::new(x as f32, y as f32, w as f32, h as f32));
+      if let Some((r, g, b)) = def.tint {
+        for pixel in sprite.get_image_data_mut() {
+          pixel[0] = (pixel[0] as u16 * r as u16 / 255) as u8;
+          pixel[1] = (pixel[1] as u16 * g as u16 / 255) as u8;
+          pixel[2] = (pixel[2] as u16 * b as u16 / 255) as u8;
+        }
+      }
+      sprites.push((id, sprite));
+    }
+
+    // Simple shelf packing, tallest sprites first so the shelves waste as little space as possible.
+    sprites.sort_by(|(a_id, a), (b_id, b)| b.height().cmp(&a.height()).then(a_id.cmp(b_id)));
+
+    let mut positions = Vec::with_capacity(sprites.len());
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for (_, sprite) in &sprites {
+      if x + sprite.width() > ATLAS_WIDTH {
+        x = 0;
+        y += shelf_height + ATLAS_PADDING;
+        shelf_height = 0;
+      }
+      positions.push((x, y));
+      x += sprite.width() + ATLAS_PADDING;
+      shelf_height = shelf_height.max(sprite.height());
+    }
+
+    let height = u16::try_from(y + shelf_height).map_err(|_| AtlasError::TooTall)?;
+    let mut atlas = Image::gen_image_color(ATLAS_WIDTH as u16, height, BLANK);
+    let mut regions = HashMap::new();
+    for ((id, sprite), (x, y)) in sprites.into_iter().zip(positions) {
+      let atlas_width = atlas.width();
+      let atlas_data = atlas.get_image_data_mut();
+      for (row, pixels) in sprite.get_image_data().chunks(sprite.width()).enumerate() {
+        let start = (y + row) * atlas_width + x;
+        atlas_data[start..start + pixels.len()].copy_from_slice(pixels);
+      }
+
+      regions
+        .insert(id, Rect::new(x as f32, y as f32, sprite.width() as f32, sprite.height() as f32));
+    }
+
+    let texture = Texture2D::from_image(&atlas);
+    texture.set_filter(FilterMode::Nearest);
+
+    Ok(SpriteAtlas { texture, regions, animations: manifest.animations })
+  }
+
+  pub fn size(&self, id: &str) -> Vec2 { self.region(id).size() }
+
+  pub fn animation(&self, id: &str) -> Option<&Animation> { self.animations.get(id) }
+
+  pub fn draw(&self, id: &str, x: f32, y: f32, color: Color, params: DrawTextureParams) {
+    draw_texture_ex(
+      self.texture,
+      x,
+      y,
+      color,
+      DrawTextureParams { source: Some(self.region(id)), ..params },
+    );
+  }
+
+  // Every id the game draws is one of the required sprites or a frame of a checked animation.
+  fn region(&self, id: &str) -> Rect {
+    *self.regions.get(id).unwrap_or_else(|| panic!("Unknown sprite `{}`", id))
+  }
+}
+
+impl Drop for SpriteAtlas {
+  fn drop(&mut self) { self.texture.delete(); }
+}
@@ -0,0 +1,486 @@
+use std::f32::consts::TAU;
+
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::tunables::Tunables;
+use crate::TONGUE_DIRECTIONS;
+
+// What a cat knows about its surroundings when deciding what to do, all positions are centers.
+pub struct Senses {
+  pub pos: Vec2,
+  pub tongue: Option<Vec2>,
+  // The closest cat other than this one.
+  pub other_cat: Option<Vec2>,
+  pub player: Vec2,
+  pub player_near: bool,
+  pub player_bouncier: bool,
+  pub bouncing: bool,
+  // A random point in the square from -1 to 1, for the choices that shouldn't be the same every time.
+  pub roll: Vec2,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CatState {
+  Patrol,
+  ChaseTongue,
+  GuardTongue,
+  InterceptPlayer,
+  // Keeps after the tongue while slowing down the players around it.
+  SlowPlayer,
+  Flee,
+  Stunned,
+  // Standing still before a pounce.
+  WindUp,
+  Pounce,
+  // Circling the tongue to push it towards the other cats.
+  Herd,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+  Bouncing,
+  PlayerNear,
+  // The player is within guarding range of the tongue.
+  PlayerNearTongue,
+  PlayerScary,
+  NoTongue,
+  // The tongue is within pouncing range and the cat has rested since its last pounce.
+  PounceReady,
+}
+
+impl Condition {
+  fn holds(self, senses: &Senses, tunables: &Tunables, rested: bool) -> bool {
+    match self {
+      Condition::Bouncing => senses.bouncing,
+      Condition::PlayerNear => senses.player_near,
+      Condition::PlayerNearTongue => senses
+        .tongue
+        .is_some_and(|tongue| tongue.distance(senses.player) < tunables.cat_guard_range),
+      Condition::PlayerScary => {
+        senses.player_bouncier && senses.pos.distance(senses.player) < tunables.cat_flee_range
+      },
+      Condition::NoTongue => senses.tongue.is_none(),
+      Condition::PounceReady => {
+        rested
+          && senses
+            .tongue
+            .is_some_and(|tongue| tongue.distance(senses.pos) < tunables.cat_pouncer_range)
+      },
+    }
+  }
+}
+
+// Picks the state of a cat from a list of rules, the first one whose condition holds wins. Cat kinds
+// are made by giving them different rules.
+#[derive(Component, Clone)]
+pub struct Brain {
+  rules: Vec<(Condition, CatState)>,
+  default: CatState,
+  pub state: CatState,
+  // What the cat would be doing if it weren't stunned, it keeps heading there while it gets bounced.
+  pub intent: CatState,
+  home: Vec2,
+  waypoint: Option<Vec2>,
+  // Counts down the current pounce phase, or the rest after it.
+  timer: f32,
+  leap: Vec2,
+  // The point the cat last steered towards, for the debug overlay.
+  pub target: Option<Vec2>,
+}
+
+impl Brain {
+  // `default` is the state used when none of the rules hold.
+  pub fn new(home: Vec2, rules: &[(Condition, CatState)], default: CatState) -> Brain {
+    let mut all_rules = vec![(Condition::Bouncing, CatState::Stunned)];
+    all_rules.extend_from_slice(rules);
+    all_rules.push((Condition::NoTongue, CatState::Patrol));
+
+    Brain {
+      rules: all_rules,
+      default,
+      state: default,
+      intent: default,
+      home,
+      waypoint: None,
+      timer: 0.0,
+      leap: Vec2::ZERO,
+      target: None,
+    }
+  }
+
+  pub fn think(&mut self, senses: &Senses, tunables: &Tunables, dt: f32) -> CatState {
+    self.timer -= dt;
+
+    // Once started a pounce plays out unless the cat gets bounced.
+    if !senses.bouncing {
+      match self.state {
+        CatState::WindUp | CatState::Pounce if self.timer > 0.0 => return self.state,
+        CatState::WindUp => {
+          self.state = CatState::Pounce;
+          self.timer = tunables.cat_pouncer_leap_time;
+          self.leap =
+            senses.tongue.map_or(Vec2::ZERO, |tongue| (tongue - senses.pos).normalize_or_zero());
+          return self.state;
+        },
+        CatState::Pounce => self.timer = tunables.cat_pouncer_rest,
+        _ => (),
+      }
+    }
+
+    let rested = self.timer <= 0.0;
+    let pick = |skip_bouncing: bool| {
+      self
+        .rules
+        .iter()
+        .filter(|(condition, _)| !(skip_bouncing && *condition == Condition::Bouncing))
+        .find(|(condition, _)| condition.holds(senses, tunables, rested))
+        .map_or(self.default, |(_, state)| *state)
+    };
+    let state = pick(false);
+    self.intent = pick(true);
+    if state == CatState::WindUp && self.state != CatState::WindUp {
+      self.timer = tunables.cat_pouncer_windup;
+    }
+    self.state = state;
+    self.state
+  }
+
+  pub fn speed_mul(&self, tunables: &Tunables) -> f32 {
+    if self.state == CatState::Pounce {
+      tunables.cat_pouncer_leap_mul
+    } else {
+      1.0
+    }
+  }
+
+  // Whether the cat has noticed a player, shown by its alert animation.
+  pub fn alert(&self) -> bool {
+    matches!(self.state, CatState::InterceptPlayer | CatState::SlowPlayer | CatState::WindUp)
+  }
+
+  // Where the cat wants to go in its current state, `None` means it stays put.
+  pub fn steer(&mut self, senses: &Senses, tunables: &Tunables) -> Option<Vec2> {
+    let state = if self.state == CatState::Stunned { self.intent } else { self.state };
+    self.target = match state {
+      CatState::Patrol => {
+        let waypoint = match self.waypoint {
+          Some(waypoint) if waypoint.distance(senses.pos) > tunables.cat_max_dest / 4.0 => waypoint,
+          _ => self.home + senses.roll * tunables.cat_patrol_radius,
+        };
+        self.waypoint = Some(waypoint);
+        Some(waypoint)
+      },
+      CatState::ChaseTongue | CatState::SlowPlayer => senses.tongue,
+      CatState::GuardTongue => {
+        // Stand on the player's side of the tongue.
+        senses.tongue.map(|tongue| {
+          tongue + (senses.player - tongue).normalize_or_zero() * tunables.cat_guard_distance
+        })
+      },
+      CatState::InterceptPlayer => Some(senses.player),
+      CatState::Flee => Some(senses.pos + (senses.pos - senses.player).normalize_or_zero()),
+      CatState::Stunned | CatState::WindUp => None,
+      CatState::Pounce => Some(senses.pos + self.leap),
+      CatState::Herd => senses.tongue.map(|tongue| {
+        let offset = (senses.pos - tongue).try_normalize().unwrap_or(Vec2::X);
+        // Get behind the tongue on the side away from the other cats, it flees away from this one.
+        let behind = senses.other_cat.and_then(|cat| (tongue - cat).try_normalize());
+        let dir = match behind {
+          Some(behind) if offset.angle_between(behind).abs() < tunables.cat_herder_orbit_step => {
+            behind
+          },
+          Some(behind) if offset.perp_dot(behind) < 0.0 => {
+            Vec2::from_angle(-tunables.cat_herder_orbit_step).rotate(offset)
+          },
+          _ => Vec2::from_angle(tunables.cat_herder_orbit_step).rotate(offset),
+        };
+        tongue + dir * tunables.cat_herder_orbit
+      }),
+    };
+    self.target
+  }
+}
+
+// How a tongue reacts to the things chasing it, levels pick one per tongue. The numbers of all but
+// the classic one are tunables.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Personality {
+  // Runs straight away from every cat wherever they are, from the player once the cats are gone. The
+  // way tongues fled before there were personalities.
+  Classic,
+  Skittish,
+  Lazy,
+  Sly,
+}
+
+struct Traits {
+  // Threats further than this are ignored, with none in range the tongue stays put.
+  alert_range: f32,
+  speed_mul: f32,
+  // Average number of jukes per second while fleeing.
+  juke_rate: f32,
+  // How much it avoids running towards walls and obstacles.
+  caution: f32,
+}
+
+impl Personality {
+  fn traits(self, tunables: &Tunables) -> Option<Traits> {
+    match self {
+      Personality::Classic => None,
+      Personality::Skittish => Some(Traits {
+        alert_range: tunables.tongue_skittish_alert_range,
+        speed_mul: tunables.tongue_skittish_speed_mul,
+        juke_rate: tunables.tongue_skittish_juke_rate,
+        caution: tunables.tongue_skittish_caution,
+      }),
+      Personality::Lazy => Some(Traits {
+        alert_range: tunables.tongue_lazy_alert_range,
+        speed_mul: tunables.tongue_lazy_speed_mul,
+        juke_rate: tunables.tongue_lazy_juke_rate,
+        caution: tunables.tongue_lazy_caution,
+      }),
+      Personality::Sly => Some(Traits {
+        alert_range: tunables.tongue_sly_alert_range,
+        speed_mul: tunables.tongue_sly_speed_mul,
+        juke_rate: tunables.tongue_sly_juke_rate,
+        caution: tunables.tongue_sly_caution,
+      }),
+    }
+  }
+}
+
+// Something the tongue runs away from, heavier threats are scarier.
+pub struct Threat {
+  pub pos: Vec2,
+  pub weight: f32,
+  pub is_cat: bool,
+}
+
+// Away from every cat with the same weight, or from the players if there are no cats.
+fn classic_flee(pos: Vec2, threats: &[Threat]) -> Option<Vec2> {
+  let is_cat = threats.iter().any(|threat| threat.is_cat);
+  let toward = threats
+    .iter()
+    .filter(|threat| threat.is_cat == is_cat)
+    .fold(Vec2::ZERO, |sum, threat| sum + (threat.pos - pos).normalize_or_zero());
+  (-toward).try_normalize()
+}
+
+#[derive(Component, Clone)]
+pub struct TongueBrain {
+  personality: Personality,
+  // Direction and time left of the current juke.
+  juke: Option<(Vec2, f32)>,
+  // The direction last fled in scaled by the speed multiplier, for the debug overlay.
+  pub flee: Option<Vec2>,
+}
+
+impl TongueBrain {
+  pub fn new(personality: Personality) -> TongueBrain {
+    TongueBrain { personality, juke: None, flee: None }
+  }
+
+  // The direction to run in and a multiplier for the speed, `None` when the tongue feels safe.
+  pub fn flee(
+    &mut self,
+    pos: Vec2,
+    threats: &[Threat],
+    arena: Rect,
+    obstacles: &[Rect],
+    tunables: &Tunables,
+    dt: f32,
+  ) -> Option<(Vec2, f32)> {
+    let Some(traits) = self.personality.traits(tunables) else {
+      let dir = classic_flee(pos, threats);
+      self.flee = dir;
+      return dir.map(|dir| (dir, 1.0));
+    };
+
+    let range = traits.alert_range;
+    let away = threats.iter().fold(Vec2::ZERO, |sum, threat| {
+      let closeness = 1.0 - (pos.distance(threat.pos) / range).min(1.0);
+      sum + (pos - threat.pos).normalize_or_zero() * threat.weight * closeness * closeness
+    });
+    let Some(away_dir) = away.try_normalize() else {
+      self.juke = None;
+      self.flee = None;
+      return None;
+    };
+
+    self.juke = match self.juke {
+      Some((side, timer)) if timer > dt => Some((side, timer - dt)),
+      _ if rand::gen_range(0.0, 1.0) < traits.juke_rate * dt => {
+        let side = if rand::gen_range(0, 2) == 0 { away_dir.perp() } else { -away_dir.perp() };
+        Some((side, tunables.tongue_juke_time))
+      },
+      _ => None,
+    };
+    let wanted = self.juke.map_or(away_dir, |(side, _)| (side + away_dir * 0.3).normalize());
+
+    // Of a handful of directions, take the one closest to the wanted one that doesn't lead into a
+    // wall or an obstacle, so the tongue slides along walls instead of pinning itself in a corner.
+    let score = |dir: Vec2| {
+      let ahead = pos + dir * tunables.tongue_lookahead;
+      let edge = (ahead.x - arena.left())
+        .min(arena.right() - ahead.x)
+        .min(ahead.y - arena.top())
+        .min(arena.bottom() - ahead.y);
+      let cramped = (1.0 - edge / tunables.tongue_wall_range).max(0.0);
+      let blocked = obstacles.iter().any(|obstacle| obstacle.contains(ahead)) as u8 as f32;
+      dir.dot(wanted) - traits.caution * (cramped + blocked)
+    };
+    let dir = (0..TONGUE_DIRECTIONS)
+      .map(|i| Vec2::from_angle(i as f32 * TAU / TONGUE_DIRECTIONS as f32))
+      .max_by(|a, b| score(*a).total_cmp(&score(*b)))
+      .unwrap_or(wanted);
+
+    // Close threats make it run at full speed.
+    let urgency = away.length().min(1.0);
+    let speed_mul = traits.speed_mul * (0.5 + 0.5 * urgency);
+    self.flee = Some(dir * speed_mul);
+    Some((dir, speed_mul))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HOME: Vec2 = Vec2::ZERO;
+
+  // A cat at home with the tongue to its right and the player far off below.
+  fn senses() -> Senses {
+    Senses {
+      pos: HOME,
+      tongue: Some(vec2(400.0, 0.0)),
+      other_cat: None,
+      player: vec2(0.0, 1000.0),
+      player_near: false,
+      player_bouncier: false,
+      bouncing: false,
+      roll: vec2(0.5, -0.5),
+    }
+  }
+
+  fn defender() -> Brain {
+    Brain::new(
+      HOME,
+      &[
+        (Condition::PlayerNear, CatState::InterceptPlayer),
+        (Condition::PlayerNearTongue, CatState::GuardTongue),
+      ],
+      CatState::ChaseTongue,
+    )
+  }
+
+  #[test]
+  fn patrols_around_home_without_a_tongue() {
+    let tunables = Tunables::default();
+    let mut brain = Brain::new(HOME, &[], CatState::ChaseTongue);
+    let senses = Senses { tongue: None, ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::Patrol);
+    let target = brain.steer(&senses, &tunables);
+    assert_eq!(target, Some(HOME + senses.roll * tunables.cat_patrol_radius));
+    // The waypoint is kept until the cat gets there.
+    let senses = Senses { roll: vec2(-1.0, 1.0), ..senses };
+    assert_eq!(brain.steer(&senses, &tunables), target);
+  }
+
+  #[test]
+  fn chases_the_tongue() {
+    let tunables = Tunables::default();
+    let mut brain = Brain::new(HOME, &[], CatState::ChaseTongue);
+    let senses = senses();
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::ChaseTongue);
+    assert_eq!(brain.steer(&senses, &tunables), senses.tongue);
+  }
+
+  #[test]
+  fn guards_the_tongue_from_the_player() {
+    let tunables = Tunables::default();
+    let mut brain = defender();
+    let senses = Senses { player: vec2(400.0, 100.0), ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::GuardTongue);
+    let expected = vec2(400.0, tunables.cat_guard_distance);
+    assert!(brain.steer(&senses, &tunables).unwrap().distance(expected) < 0.01);
+  }
+
+  #[test]
+  fn intercepts_a_player_nearby() {
+    let tunables = Tunables::default();
+    let mut brain = defender();
+    let senses = Senses { player: vec2(50.0, 0.0), player_near: true, ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::InterceptPlayer);
+    assert!(brain.alert());
+    assert_eq!(brain.steer(&senses, &tunables), Some(senses.player));
+  }
+
+  #[test]
+  fn slows_a_player_nearby_while_chasing() {
+    let tunables = Tunables::default();
+    let mut brain =
+      Brain::new(HOME, &[(Condition::PlayerNear, CatState::SlowPlayer)], CatState::ChaseTongue);
+    let senses = Senses { player: vec2(50.0, 0.0), player_near: true, ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::SlowPlayer);
+    assert!(brain.alert());
+    assert_eq!(brain.steer(&senses, &tunables), senses.tongue);
+  }
+
+  #[test]
+  fn flees_a_bouncier_player() {
+    let tunables = Tunables::default();
+    let mut brain =
+      Brain::new(HOME, &[(Condition::PlayerScary, CatState::Flee)], CatState::ChaseTongue);
+    let senses = Senses { player: vec2(50.0, 0.0), player_bouncier: true, ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::Flee);
+    assert_eq!(brain.steer(&senses, &tunables), Some(vec2(-1.0, 0.0)));
+
+    // Out of range the player isn't scary.
+    let senses = Senses { player: vec2(0.0, tunables.cat_flee_range + 1.0), ..senses };
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::ChaseTongue);
+  }
+
+  #[test]
+  fn stunned_cats_keep_heading_where_they_were_going() {
+    let tunables = Tunables::default();
+    let mut brain = defender();
+    let senses = Senses { player: vec2(50.0, 0.0), player_near: true, bouncing: true, ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::Stunned);
+    assert_eq!(brain.intent, CatState::InterceptPlayer);
+    assert!(!brain.alert());
+    assert_eq!(brain.steer(&senses, &tunables), Some(senses.player));
+  }
+
+  #[test]
+  fn pounces_after_winding_up_and_rests() {
+    let tunables = Tunables::default();
+    let mut brain =
+      Brain::new(HOME, &[(Condition::PounceReady, CatState::WindUp)], CatState::ChaseTongue);
+    let senses = Senses { tongue: Some(vec2(100.0, 0.0)), ..senses() };
+
+    assert_eq!(brain.think(&senses, &tunables, 0.1), CatState::WindUp);
+    assert_eq!(brain.steer(&senses, &tunables), None);
+    assert_eq!(
+      brain.think(&senses, &tunables, tunables.cat_pouncer_windup / 2.0),
+      CatState::WindUp
+    );
+
+    assert_eq!(brain.think(&senses, &tunables, tunables.cat_pouncer_windup), CatState::Pounce);
+    assert_eq!(brain.speed_mul(&tunables), tunables.cat_pouncer_leap_mul);
+    assert_eq!(brain.steer(&senses, &tunables), Some(vec2(1.0, 0.0)));
+
+    // After the leap it rests before it can pounce again.
+    let leap_time = tunables.cat_pouncer_leap_time;
+    assert_eq!(brain.think(&senses, &tunables, leap_time + 0.01), CatState::ChaseTongue);
+    assert_eq!(brain.speed_mul(&tunables), 1.0);
+    assert_eq!(brain.think(&senses, &tunables, tunables.cat_pouncer_rest), CatState::WindUp);
+  }
+}
@@ -0,0 +1,234 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::time::TimeScale;
+use crate::versus::Driven;
+use crate::{
+  Arena,
+  Cat,
+  Player,
+  Tongue,
+};
+
+// Angular frequency of the follow spring, higher catches up faster.
+const CAMERA_STIFFNESS: f32 = 6.0;
+const CAMERA_LOOK_AHEAD: f32 = 90.0;
+const CAMERA_MIN_ZOOM: f32 = 0.6;
+const CAMERA_ZOOM_SPEED: f32 = 2.0;
+// Cats further than this from the player don't make the camera zoom out.
+const CAMERA_FRAME_DISTANCE: f32 = 500.0;
+const CAMERA_FRAME_PADDING: f32 = 60.0;
+const CAMERA_MAX_SHAKE: f32 = 18.0;
+const CAMERA_TRAUMA_DECAY: f32 = 1.5;
+// Split screen only goes back to a shared view once the players fit this much above the minimum
+// zoom, so it doesn't flicker at the edge.
+const CAMERA_SPLIT_HYSTERESIS: f32 = 0.1;
+
+// A part of the screen showing the world through its own camera.
+pub struct View {
+  pub camera: Camera2D,
+  pub viewport: Rect,
+}
+
+impl View {
+  pub fn world_to_screen(&self, point: Vec2) -> Vec2 {
+    self.camera.world_to_screen(point) / vec2(screen_width(), screen_height())
+      * self.viewport.size()
+      + self.viewport.point()
+  }
+
+  pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+    self.camera.screen_to_world(
+      (point - self.viewport.point()) / self.viewport.size()
+        * vec2(screen_width(), screen_height()),
+    )
+  }
+
+  // Maps a rect from world space to screen space, including its size so zooming scales sprites.
+  pub fn world_to_screen_rect(&self, rect: Rect) -> Rect {
+    let top_left = self.world_to_screen(rect.point());
+    let bottom_right = self.world_to_screen(rect.point() + rect.size());
+    Rect::new(top_left.x, top_left.y, bottom_right.x - top_left.x, bottom_right.y - top_left.y)
+  }
+}
+
+// One view for the whole screen, or one per player when they are too far apart for split screen.
+pub struct Views(pub Vec<View>);
+
+impl Views {
+  pub fn new() -> Views {
+    let screen = Rect::new(0.0, 0.0, screen_width(), screen_height());
+    Views(vec![View { camera: Camera2D::from_display_rect(screen), viewport: screen }])
+  }
+}
+
+fn set_clip(clip: Option<Rect>) {
+  let clip = clip.map(|rect| (rect.x as i32, rect.y as i32, rect.w as i32, rect.h as i32));
+  unsafe { get_internal_gl().quad_gl.scissor(clip) };
+}
+
+// Runs `draw` once for every view, with drawing clipped to that view.
+pub fn in_views(views: &Views, mut draw: impl FnMut(&View)) {
+  for view in &views.0 {
+    set_clip(Some(view.viewport));
+    draw(view);
+  }
+  set_clip(None);
+}
+
+#[derive(Clone)]
+struct Rig {
+  pos: Vec2,
+  vel: Vec2,
+  last_focus: Option<Vec2>,
+  zoom: f32,
+}
+
+pub struct CameraController {
+  rigs: Vec<Rig>,
+  trauma: f32,
+}
+
+impl CameraController {
+  pub fn new() -> CameraController {
+    CameraController {
+      rigs: vec![Rig {
+        pos: vec2(screen_width(), screen_height()) / 2.0,
+        vel: Vec2::ZERO,
+        last_focus: None,
+        zoom: 1.0,
+      }],
+      trauma: 0.0,
+    }
+  }
+
+  // Trauma adds up and decays over time, the shake grows with its square so small hits stay subtle.
+  pub fn add_trauma(&mut self, amount: f32) { self.trauma = (self.trauma + amount).min(1.0); }
+}
+
+// The zoom that keeps every point on a view of this size centered on `focus`.
+fn frame_zoom(focus: Vec2, points: impl Iterator<Item = Vec2>, size: Vec2) -> f32 {
+  points
+    .map(|point| {
+      let extent = (point - focus).abs().max(Vec2::ONE);
+      ((size / 2.0 - CAMERA_FRAME_PADDING) / extent).min_element()
+    })
+    .fold(1.0_f32, f32::min)
+}
+
+fn average(points: &[Vec2]) -> Vec2 {
+  points.iter().fold(Vec2::ZERO, |sum, point| sum + *point) / points.len() as f32
+}
+
+// A new round starts from the middle of the screen instead of gliding over from the last one.
+pub fn reset_camera(mut controller: ResMut<CameraController>) {
+  *controller = CameraController::new();
+}
+
+pub fn update_camera(
+  mut views: ResMut<Views>,
+  mut controller: ResMut<CameraController>,
+  arena: Res<Arena>,
+  players: Query<&Player>,
+  tongues: Query<&Tongue>,
+  cats: Query<(&Cat, Option<&Driven>)>,
+  time_scale: Res<TimeScale>,
+) {
+  let dt = time_scale.delta;
+  let screen = vec2(screen_width(), screen_height());
+
+  let mut players: Vec<&Player> = players.iter().collect();
+  players.sort_by_key(|player| player.id.index());
+  // Cats driven by a player get followed like players.
+  let player_rects: Vec<Rect> = players
+    .iter()
+    .map(|player| player.rect)
+    .chain(cats.iter().filter(|(_, driven)| driven.is_some()).map(|(cat, _)| cat.rect))
+    .collect();
+  let player_centers: Vec<Vec2> = player_rects.iter().map(|rect| rect.center()).collect();
+
+  // Split the screen when a shared view can't fit every player.
+  let split = player_centers.len() > 1 && {
+    let needed_zoom = frame_zoom(
+      average(&player_centers),
+      player_rects.iter().flat_map(|rect| [rect.point(), rect.point() + rect.size()]),
+      screen,
+    );
+    let threshold = if controller.rigs.len() > 1 {
+      CAMERA_MIN_ZOOM + CAMERA_SPLIT_HYSTERESIS
+    } else {
+      CAMERA_MIN_ZOOM
+    };
+    needed_zoom < threshold
+  };
+  if split && controller.rigs.len() != player_centers.len() {
+    let rig = controller.rigs[0].clone();
+    controller.rigs = vec![rig; player_centers.len()];
+  } else if !split && controller.rigs.len() > 1 {
+    let pos = average(&controller.rigs.iter().map(|rig| rig.pos).collect::<Vec<_>>());
+    controller.rigs.truncate(1);
+    controller.rigs[0].pos = pos;
+  }
+
+  controller.trauma = (controller.trauma - CAMERA_TRAUMA_DECAY * dt).max(0.0);
+  let shake = controller.trauma * controller.trauma * CAMERA_MAX_SHAKE;
+
+  let rig_count = controller.rigs.len();
+  views.0.clear();
+  for (i, rig) in controller.rigs.iter_mut().enumerate() {
+    let viewport =
+      Rect::new(screen.x * i as f32 / rig_count as f32, 0.0, screen.x / rig_count as f32, screen.y);
+
+    // A shared view follows every player, split views follow their own.
+    let followed = if split { &player_rects[i..=i] } else { &player_rects[..] };
+    if !followed.is_empty() {
+      let focus = average(&followed.iter().map(|rect| rect.center()).collect::<Vec<_>>());
+
+      let moved = rig.last_focus.map_or(Vec2::ZERO, |last_focus| focus - last_focus);
+      rig.last_focus = Some(focus);
+
+      // Zoom out just enough to keep the players, the tongues and the cats around them on screen.
+      let framed = followed
+        .iter()
+        .copied()
+        .chain(tongues.iter().map(|tongue| tongue.rect))
+        .chain(
+          cats
+            .iter()
+            .map(|(cat, _)| cat.rect)
+            .filter(|rect| rect.center().distance(focus) < CAMERA_FRAME_DISTANCE),
+        )
+        .flat_map(|rect| [rect.point(), rect.point() + rect.size()]);
+      let target_zoom = frame_zoom(focus, framed, viewport.size()).clamp(CAMERA_MIN_ZOOM, 1.0);
+      rig.zoom += (target_zoom - rig.zoom) * (CAMERA_ZOOM_SPEED * dt).min(1.0);
+
+      // Critically damped spring towards the focus point.
+      let target = focus + moved.normalize_or_zero() * CAMERA_LOOK_AHEAD;
+      let accel =
+        (target - rig.pos) * CAMERA_STIFFNESS * CAMERA_STIFFNESS - rig.vel * 2.0 * CAMERA_STIFFNESS;
+      rig.vel += accel * dt;
+      rig.pos += rig.vel * dt;
+    }
+
+    // Nothing keeps the players inside the arena, the camera goes as far as it takes to follow them.
+    let half_view = viewport.size() / rig.zoom / 2.0;
+    let bounds = followed.iter().fold(arena.0, |bounds, rect| bounds.combine_with(*rect));
+    let clamp_axis = |pos: f32, min: f32, max: f32, half: f32| {
+      if max - min < half * 2.0 {
+        (min + max) / 2.0
+      } else {
+        pos.clamp(min + half, max - half)
+      }
+    };
+    rig.pos = vec2(
+      clamp_axis(rig.pos.x, bounds.left(), bounds.right(), half_view.x),
+      clamp_axis(rig.pos.y, bounds.top(), bounds.bottom(), half_view.y),
+    );
+
+    let offset = vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0)) * shake;
+    let mut camera =
+      Camera2D::from_display_rect(Rect::new(0.0, 0.0, half_view.x * 2.0, half_view.y * 2.0));
+    camera.target = rig.pos + offset;
+    views.0.push(View { camera, viewport });
+  }
+}
@@ -0,0 +1,228 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::net::NetStatus;
+use crate::{
+  Cheated,
+  GameState,
+  TextureManager,
+};
+
+const CONSOLE_FONT_SIZE: u16 = 22;
+const CONSOLE_LINE_HEIGHT: f32 = 26.0;
+const CONSOLE_MARGIN: f32 = 10.0;
+// Of the screen height.
+const CONSOLE_HEIGHT: f32 = 0.4;
+const CONSOLE_LOG_LINES: usize = 100;
+const CONSOLE_HISTORY: usize = 50;
+
+// Changing the world on one side only would desync an online match, so the developer tools can only
+// look at it there.
+pub fn blocked_online(net_status: Option<&NetStatus>) -> bool { net_status.is_some() }
+
+// A command typed into the console, modules list the ones for their systems next to them.
+#[derive(Clone, Copy)]
+pub struct Command {
+  pub name: &'static str,
+  pub usage: &'static str,
+  // The words the argument at the given index can be, for autocompletion.
+  pub complete: fn(&World, usize) -> Vec<String>,
+  // What happened, or why nothing did.
+  pub run: fn(&mut World, &[&str]) -> Result<String, String>,
+}
+
+pub fn no_completion(_: &World, _: usize) -> Vec<String> { Vec::new() }
+
+// Completes from a fixed list of words per argument.
+pub fn complete_from(lists: &[&[&str]], index: usize) -> Vec<String> {
+  lists.get(index).map_or(Vec::new(), |list| list.iter().map(|word| word.to_string()).collect())
+}
+
+pub fn arg<T: std::str::FromStr>(args: &[&str], index: usize) -> Result<T, String> {
+  let arg = args.get(index).ok_or_else(|| "Missing an argument".to_string())?;
+  arg.parse().map_err(|_| format!("`{}` isn't valid here", arg))
+}
+
+// Most commands only make sense with a level loaded.
+pub fn require_playing(world: &World) -> Result<(), String> {
+  if *world.resource::<State<GameState>>().current() != GameState::Playing {
+    return Err("Only works while playing".to_string());
+  }
+  Ok(())
+}
+
+#[derive(Default)]
+pub struct Console {
+  open: bool,
+  input: String,
+  history: Vec<String>,
+  // The entry of the history shown in the input while browsing it.
+  browsing: Option<usize>,
+  log: Vec<String>,
+  commands: Vec<Command>,
+}
+
+impl Console {
+  pub fn is_open(&self) -> bool { self.open }
+
+  pub fn register(&mut self, commands: &[Command]) { self.commands.extend_from_slice(commands); }
+
+  fn print(&mut self, line: String) {
+    self.log.push(line);
+    if self.log.len() > CONSOLE_LOG_LINES {
+      self.log.remove(0);
+    }
+  }
+
+  fn browse(&mut self, older: bool) {
+    let last = self.history.len().checked_sub(1);
+    self.browsing = match (self.browsing, older) {
+      (None, true) => last,
+      (Some(index), true) => Some(index.saturating_sub(1)),
+      (Some(index), false) if Some(index) != last => Some(index + 1),
+      _ => None,
+    };
+    self.input = self.browsing.map_or(String::new(), |index| self.history[index].clone());
+  }
+
+  // Completes the last word as far as it's the same for every candidate, and lists them if there's
+  // more than one.
+  fn complete(&mut self, world: &World) {
+    let words: Vec<&str> = self.input.split(' ').collect();
+    let (partial, before) = words.split_last().unwrap();
+    let candidates: Vec<String> = match before.split_first() {
+      None => std::iter::once("help")
+        .chain(self.commands.iter().map(|command| command.name))
+        .map(|name| name.to_string())
+        .collect(),
+      Some((name, args)) => self
+        .commands
+        .iter()
+        .find(|command| command.name == *name)
+        .map_or(Vec::new(), |command| (command.complete)(world, args.len())),
+    };
+    let matching: Vec<String> =
+      candidates.into_iter().filter(|candidate| candidate.starts_with(partial)).collect();
+
+    let Some(first) = matching.first() else {
+      return;
+    };
+    let common = matching.iter().fold(first.clone(), |common, candidate| {
+      common.chars().zip(candidate.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect()
+    });
+    let mut input = before.iter().map(|word| format!("{} ", word)).collect::<String>() + &common;
+    if matching.len() == 1 {
+      input.push(' ');
+    } else {
+      self.print(matching.join("  "));
+    }
+    self.input = input;
+  }
+
+  fn execute(&mut self, world: &mut World) {
+    let line = std::mem::take(&mut self.input);
+    self.browsing = None;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = words.split_first() else {
+      return;
+    };
+    self.print(format!("> {}", line));
+    if self.history.last() != Some(&line) {
+      self.history.push(line.clone());
+      if self.history.len() > CONSOLE_HISTORY {
+        self.history.remove(0);
+      }
+    }
+
+    if *name == "help" {
+      let usages: Vec<String> =
+        self.commands.iter().map(|command| command.usage.to_string()).collect();
+      for usage in usages {
+        self.print(usage);
+      }
+      return;
+    }
+    let Some(command) = self.commands.iter().find(|command| command.name == *name).copied() else {
+      self.print(format!("Unknown command `{}`, try `help`", name));
+      return;
+    };
+    if blocked_online(world.get_resource::<NetStatus>()) {
+      self.print("Commands don't work online".to_string());
+      return;
+    }
+    match (command.run)(world, args) {
+      Ok(result) => {
+        world.resource_mut::<Cheated>().0 = true;
+        self.print(result);
+      },
+      Err(err) => {
+        self.print(err);
+        self.print(format!("Usage: {}", command.usage));
+      },
+    }
+  }
+
+  fn draw(&self, tm: &TextureManager) {
+    let height = screen_height() * CONSOLE_HEIGHT;
+    draw_rectangle(0.0, 0.0, screen_width(), height, Color { a: 0.9, ..tm.theme.bg });
+    draw_line(0.0, height, screen_width(), height, 2.0, tm.theme.fg);
+
+    let params = TextParams {
+      font: tm.yoster_island,
+      font_size: CONSOLE_FONT_SIZE,
+      color: WHITE,
+      ..Default::default()
+    };
+    let mut y = height - CONSOLE_MARGIN;
+    draw_text_ex(&format!("> {}_", self.input), CONSOLE_MARGIN, y, params);
+    for line in self.log.iter().rev() {
+      y -= CONSOLE_LINE_HEIGHT;
+      if y < CONSOLE_LINE_HEIGHT {
+        break;
+      }
+      draw_text_ex(line, CONSOLE_MARGIN, y, TextParams { color: tm.theme.fg, ..params });
+    }
+  }
+}
+
+// Reads the keyboard, runs commands and draws the console on top of everything, it needs the whole
+// world for the commands.
+pub fn run_console(world: &mut World) {
+  world.resource_scope(|world, mut console: Mut<Console>| {
+    // Characters queue up even while nothing reads them, so they're always taken out.
+    let mut typed = Vec::new();
+    while let Some(character) = get_char_pressed() {
+      typed.push(character);
+    }
+    if is_key_pressed(KeyCode::GraveAccent) {
+      console.open = !console.open;
+      return;
+    }
+    if !console.open {
+      return;
+    }
+
+    for character in typed {
+      if !character.is_control() && character != '`' {
+        console.input.push(character);
+      }
+    }
+    if is_key_pressed(KeyCode::Backspace) {
+      console.input.pop();
+    }
+    if is_key_pressed(KeyCode::Up) {
+      console.browse(true);
+    }
+    if is_key_pressed(KeyCode::Down) {
+      console.browse(false);
+    }
+    if is_key_pressed(KeyCode::Tab) {
+      console.complete(world);
+    }
+    if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+      console.execute(world);
+    }
+
+    console.draw(world.resource::<TextureManager>());
+  });
+}
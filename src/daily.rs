@@ -0,0 +1,227 @@
+use bevy_ecs::prelude::*;
+use macroquad::audio::play_sound_once;
+use macroquad::miniquad::date;
+use macroquad::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+use crate::difficulty::{
+  Difficulty,
+  Preset,
+};
+use crate::generator::{
+  generate,
+  Generator,
+};
+use crate::locale::Locale;
+use crate::{
+  draw_ui_button,
+  save,
+  Cheated,
+  DeltaTime,
+  GameState,
+  Outcome,
+  PlayMode,
+  PowerUpKind,
+  RoundOver,
+  TextureManager,
+};
+
+const DAILY_DIFFICULTY: u32 = 5;
+const DAILY_FILE: &str = "daily.ron";
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Date {
+  year: i32,
+  month: u32,
+  day: u32,
+}
+
+impl Date {
+  // The day changes at midnight UTC, so everyone plays the same level at the same time.
+  fn today() -> Date { Date::from_days((date::now() / 86400.0).floor() as i64) }
+
+  // Days since 1970 to a date, from http://howardhinnant.github.io/date_algorithms.html.
+  fn from_days(days: i64) -> Date {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+      (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    Date { year: year as i32, month: month as u32, day: day as u32 }
+  }
+
+  // Readable, and the same for everyone playing on the same day.
+  fn seed(&self) -> u32 { self.year as u32 * 10000 + self.month * 100 + self.day }
+
+  fn powerup(&self) -> PowerUpKind {
+    match self.seed() % 3 {
+      0 => PowerUpKind::SpeedUp,
+      1 => PowerUpKind::NoBounce,
+      _ => PowerUpKind::Bouncier,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+struct DailyResult {
+  date: Date,
+  // How long collecting the tongues took, `None` if a cat got one first.
+  time: Option<f32>,
+  // Results saved before there were difficulties count as normal.
+  #[serde(default)]
+  difficulty: Preset,
+}
+
+impl DailyResult {
+  fn text(&self, locale: &Locale) -> String {
+    let difficulty = locale.text(self.difficulty.name());
+    match self.time {
+      Some(time) => locale
+        .format("daily.time", &[("time", &format!("{:.1}", time)), ("difficulty", &difficulty)]),
+      None => locale.format("daily.lost", &[("difficulty", &difficulty)]),
+    }
+  }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct DailyHistory(Vec<DailyResult>);
+
+impl DailyHistory {
+  pub fn load() -> DailyHistory { save::load(DAILY_FILE) }
+
+  fn get(&self, date: Date) -> Option<&DailyResult> {
+    self.0.iter().find(|result| result.date == date)
+  }
+
+  fn record(&mut self, result: DailyResult) {
+    self.0.retain(|other| other.date != result.date);
+    self.0.push(result);
+    save::store(DAILY_FILE, self);
+  }
+}
+
+#[derive(Default)]
+pub struct Daily {
+  // The day of the scored attempt being played, replays of a day already played aren't scored.
+  attempt: Option<Date>,
+  timer: f32,
+}
+
+pub fn time_daily(
+  dt: Res<DeltaTime>,
+  round_over: Res<RoundOver>,
+  difficulty: Res<Difficulty>,
+  cheated: Res<Cheated>,
+  mut daily: ResMut<Daily>,
+  mut history: ResMut<DailyHistory>,
+) {
+  let Some(date) = daily.attempt else {
+    return;
+  };
+
+  daily.timer += dt.0;
+  let Some(outcome) = round_over.0 else {
+    return;
+  };
+  if outcome == Outcome::Won && !cheated.0 {
+    history.record(DailyResult { date, time: Some(daily.timer), difficulty: difficulty.preset });
+  }
+  daily.attempt = None;
+}
+
+pub fn draw_daily(tm: Res<TextureManager>, locale: Res<Locale>, daily: Res<Daily>) {
+  if daily.attempt.is_none() {
+    return;
+  }
+
+  let rect = Rect::new(screen_width() / 2.0 - 100.0, 10.0, 200.0, 50.0);
+  let text = locale.format("daily.timer", &[("time", &format!("{:.1}", daily.timer))]);
+  draw_ui_button(&tm, &rect, &text);
+}
+
+fn daily_button() -> Rect {
+  Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 250.0, 500.0, 50.0)
+}
+
+// The daily button and how the past days went, on the main menu.
+pub fn draw_daily_select(tm: Res<TextureManager>, locale: Res<Locale>, history: Res<DailyHistory>) {
+  let text = match history.get(Date::today()) {
+    Some(result) => locale.format("daily.button", &[("result", &result.text(&locale))]),
+    None => locale.text("daily.challenge"),
+  };
+  draw_ui_button(&tm, &daily_button(), &text);
+
+  if history.0.is_empty() {
+    return;
+  }
+
+  let won = history.0.iter().filter(|result| result.time.is_some()).count();
+  let count = history.0.len();
+  let text = match history.0.iter().filter_map(|result| result.time).min_by(f32::total_cmp) {
+    Some(best) => locale.format(
+      "daily.summary",
+      &[("won", &won), ("count", &count), ("best", &format!("{:.1}", best))],
+    ),
+    None => locale.format("daily.summary_no_best", &[("won", &won), ("count", &count)]),
+  };
+  let rect = Rect::new(screen_width() / 2.0 - 250.0, 20.0, 500.0, 50.0);
+  draw_ui_button(&tm, &rect, &text);
+}
+
+// Starts the level of the day.
+pub fn daily_select(
+  tm: Res<TextureManager>,
+  mut game_state: ResMut<State<GameState>>,
+  mut mode: ResMut<PlayMode>,
+  mut generator: ResMut<Generator>,
+  mut daily: ResMut<Daily>,
+  mut history: ResMut<DailyHistory>,
+  difficulty: Res<Difficulty>,
+) {
+  let today = Date::today();
+
+  if daily_button().contains(mouse_position().into()) && is_mouse_button_pressed(MouseButton::Left)
+  {
+    play_sound_once(tm.ui);
+    let mut layout = generate(today.seed(), DAILY_DIFFICULTY);
+    layout.powerup = Some(today.powerup());
+    generator.seed = today.seed();
+    generator.difficulty = DAILY_DIFFICULTY;
+    generator.layout = Some(layout);
+    *mode = PlayMode::Solo;
+
+    daily.timer = 0.0;
+    daily.attempt = None;
+    if history.get(today).is_none() {
+      // Counts as lost until it's won, so closing the game halfway doesn't give another try.
+      history.record(DailyResult { date: today, time: None, difficulty: difficulty.preset });
+      daily.attempt = Some(today);
+    }
+    let _ = game_state.overwrite_set(GameState::Playing);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn days_since_1970_make_dates() {
+    let date = |year, month, day| Date { year, month, day };
+    assert!(Date::from_days(0) == date(1970, 1, 1));
+    assert!(Date::from_days(-1) == date(1969, 12, 31));
+    // Leap days, in a year divisible by 400 and in an ordinary leap year.
+    assert!(Date::from_days(11016) == date(2000, 2, 29));
+    assert!(Date::from_days(19782) == date(2024, 2, 29));
+    assert!(Date::from_days(19417) == date(2023, 3, 1));
+    assert!(Date::from_days(20513) == date(2026, 3, 1));
+  }
+}
@@ -0,0 +1,155 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::behavior::{
+  Brain,
+  TongueBrain,
+};
+use crate::camera::{
+  in_views,
+  View,
+  Views,
+};
+use crate::tunables::Tunables;
+use crate::{
+  Cat,
+  Obstacle,
+  Pathfinder,
+  Player,
+  TextureManager,
+  Tongue,
+  FONT_SIZE,
+};
+
+const DEBUG_LINE_WIDTH: f32 = 2.0;
+const DEBUG_MARGIN: f32 = 10.0;
+// How long the flee vector is drawn at a speed multiplier of one.
+const DEBUG_FLEE_LENGTH: f32 = 100.0;
+
+const RECT_COLOR: Color = color_u8!(0, 255, 0, 255);
+const PROXIMITY_COLOR: Color = color_u8!(255, 200, 0, 255);
+const AURA_COLOR: Color = color_u8!(0, 200, 255, 255);
+const TARGET_COLOR: Color = color_u8!(255, 60, 60, 255);
+const BOUNCE_COLOR: Color = color_u8!(255, 0, 255, 255);
+const FLEE_COLOR: Color = color_u8!(255, 110, 150, 255);
+const PATH_COLOR: Color = color_u8!(255, 255, 255, 160);
+
+pub struct DebugOverlay(pub bool);
+
+fn draw_rect(view: &View, rect: Rect, color: Color) {
+  let rect = view.world_to_screen_rect(rect);
+  draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, DEBUG_LINE_WIDTH, color);
+}
+
+fn draw_segment(view: &View, from: Vec2, to: Vec2, color: Color) {
+  let from = view.world_to_screen(from);
+  let to = view.world_to_screen(to);
+  draw_line(from.x, from.y, to.x, to.y, DEBUG_LINE_WIDTH, color);
+}
+
+// Bounce and path destinations are where the top left corner ends up, drawn from the center.
+fn draw_dest(view: &View, rect: Rect, dest: Vec2, color: Color) {
+  draw_segment(view, rect.center(), dest + rect.size() / 2.0, color);
+}
+
+pub fn toggle_debug(mut overlay: ResMut<DebugOverlay>) {
+  if is_key_pressed(KeyCode::F3) {
+    overlay.0 = !overlay.0;
+  }
+}
+
+// What collides with what, and the areas cats and maneki act in.
+pub fn draw_debug_rects(
+  overlay: Res<DebugOverlay>,
+  views: Res<Views>,
+  tunables: Res<Tunables>,
+  players: Query<&Player>,
+  tongues: Query<&Tongue>,
+  cats: Query<&Cat>,
+  obstacles: Query<&Obstacle>,
+) {
+  if !overlay.0 {
+    return;
+  }
+
+  in_views(&views, |view| {
+    for player in &players {
+      draw_rect(view, player.rect, RECT_COLOR);
+    }
+    for tongue in &tongues {
+      draw_rect(view, tongue.rect, RECT_COLOR);
+    }
+    for cat in &cats {
+      draw_rect(view, cat.rect, RECT_COLOR);
+      let proximity = cat.proximity(&tunables);
+      if proximity.w > 0.0 {
+        draw_rect(view, proximity, PROXIMITY_COLOR);
+      }
+    }
+    for obstacle in &obstacles {
+      draw_rect(view, obstacle.rect, RECT_COLOR);
+      draw_rect(view, obstacle.proximity(&tunables), AURA_COLOR);
+    }
+  });
+}
+
+// Where everything is headed and why.
+pub fn draw_debug_targets(
+  overlay: Res<DebugOverlay>,
+  views: Res<Views>,
+  players: Query<&Player>,
+  tongues: Query<(&Tongue, &Pathfinder, &TongueBrain)>,
+  cats: Query<(&Cat, &Pathfinder, Option<&Brain>)>,
+) {
+  if !overlay.0 {
+    return;
+  }
+
+  in_views(&views, |view| {
+    for player in &players {
+      if player.bounce_percentage.is_some() {
+        draw_dest(view, player.rect, player.bounce_dest, BOUNCE_COLOR);
+      }
+    }
+    for (tongue, pathfinder, brain) in &tongues {
+      if let Some(dest) = pathfinder.dest {
+        draw_dest(view, tongue.rect, dest, PATH_COLOR);
+      }
+      if let Some(flee) = brain.flee {
+        let center = tongue.rect.center();
+        draw_segment(view, center, center + flee * DEBUG_FLEE_LENGTH, FLEE_COLOR);
+      }
+    }
+    for (cat, pathfinder, brain) in &cats {
+      if let Some(dest) = pathfinder.dest {
+        draw_dest(view, cat.rect, dest, PATH_COLOR);
+      }
+      if let Some(target) = brain.and_then(|brain| brain.target) {
+        draw_segment(view, cat.rect.center(), target, TARGET_COLOR);
+      }
+      if cat.bounce_percentage.is_some() {
+        draw_dest(view, cat.rect, cat.bounce_dest, BOUNCE_COLOR);
+      }
+    }
+  });
+}
+
+pub fn draw_frame_stats(overlay: Res<DebugOverlay>, tm: Res<TextureManager>) {
+  if !overlay.0 {
+    return;
+  }
+
+  let text = format!("{} FPS ({:.1} ms)", get_fps(), get_frame_time() * 1000.0);
+  let text_measure = measure_text(&text, Some(tm.yoster_island), FONT_SIZE, 1.0);
+  draw_text_ex(
+    &text,
+    screen_width() - text_measure.width - DEBUG_MARGIN,
+    screen_height() - DEBUG_MARGIN,
+    TextParams {
+      font: tm.yoster_island,
+      font_size: FONT_SIZE,
+      color: RECT_COLOR,
+      ..Default::default()
+    },
+  );
+}
@@ -0,0 +1,139 @@
+use bevy_ecs::prelude::*;
+use macroquad::audio::play_sound_once;
+use macroquad::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+use crate::locale::Locale;
+use crate::{
+  draw_ui_button,
+  save,
+  JustPressedBackButton,
+  TextureManager,
+};
+
+const DIFFICULTY_FILE: &str = "difficulty.ron";
+// The multipliers of `Custom` have to be in this range, they come from a file that's edited by hand.
+const SCALING_RANGE: std::ops::RangeInclusive<f32> = 0.0..=5.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Preset {
+  Easy,
+  #[default]
+  Normal,
+  Hard,
+  // Whatever is in the save file.
+  Custom,
+}
+
+impl Preset {
+  // The id of its name in the string tables.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Preset::Easy => "difficulty.easy",
+      Preset::Normal => "difficulty.normal",
+      Preset::Hard => "difficulty.hard",
+      Preset::Custom => "difficulty.custom",
+    }
+  }
+}
+
+// Multipliers of the constants the difficulty changes.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Scaling {
+  pub cat_speed: f32,
+  // Of the range defenders and slowing cats notice the player in.
+  pub proximity: f32,
+  // Of how much slowing cats slow the player down.
+  pub slowing: f32,
+  // Of how far cats and the player bounce off each other.
+  pub bounce: f32,
+  pub powerup_cooldown: f32,
+}
+
+const EASY: Scaling =
+  Scaling { cat_speed: 0.8, proximity: 0.8, slowing: 0.6, bounce: 1.25, powerup_cooldown: 0.75 };
+const NORMAL: Scaling =
+  Scaling { cat_speed: 1.0, proximity: 1.0, slowing: 1.0, bounce: 1.0, powerup_cooldown: 1.0 };
+const HARD: Scaling =
+  Scaling { cat_speed: 1.2, proximity: 1.25, slowing: 1.4, bounce: 0.8, powerup_cooldown: 1.5 };
+
+impl Scaling {
+  // Multipliers out of range play like `Normal`.
+  fn validate(&mut self) {
+    for (name, value, normal) in [
+      ("cat_speed", &mut self.cat_speed, NORMAL.cat_speed),
+      ("proximity", &mut self.proximity, NORMAL.proximity),
+      ("slowing", &mut self.slowing, NORMAL.slowing),
+      ("bounce", &mut self.bounce, NORMAL.bounce),
+      ("powerup_cooldown", &mut self.powerup_cooldown, NORMAL.powerup_cooldown),
+    ] {
+      if !SCALING_RANGE.contains(value) {
+        warn!(
+          "`{}` in {} is {}, it has to be from {} to {}",
+          name,
+          DIFFICULTY_FILE,
+          value,
+          SCALING_RANGE.start(),
+          SCALING_RANGE.end()
+        );
+        *value = normal;
+      }
+    }
+  }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Difficulty {
+  pub preset: Preset,
+  custom: Scaling,
+}
+
+impl Default for Difficulty {
+  fn default() -> Difficulty { Difficulty { preset: Preset::Normal, custom: NORMAL } }
+}
+
+impl Difficulty {
+  pub fn load() -> Difficulty {
+    let mut difficulty: Difficulty = save::load(DIFFICULTY_FILE);
+    difficulty.custom.validate();
+    difficulty
+  }
+
+  pub fn scaling(&self) -> Scaling {
+    match self.preset {
+      Preset::Easy => EASY,
+      Preset::Normal => NORMAL,
+      Preset::Hard => HARD,
+      Preset::Custom => self.custom,
+    }
+  }
+}
+
+// The button picking the difficulty, next to the exit button of the main menu.
+pub fn difficulty_select(
+  tm: Res<TextureManager>,
+  locale: Res<Locale>,
+  mut difficulty: ResMut<Difficulty>,
+  just_pressed_back_button: Res<JustPressedBackButton>,
+) {
+  let button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 100.0, 240.0, 50.0);
+  draw_ui_button(&tm, &button, &locale.text(difficulty.preset.name()));
+
+  if button.contains(mouse_position().into())
+    && is_mouse_button_pressed(MouseButton::Left)
+    && !just_pressed_back_button.0
+  {
+    play_sound_once(tm.ui);
+    difficulty.preset = match difficulty.preset {
+      Preset::Easy => Preset::Normal,
+      Preset::Normal => Preset::Hard,
+      Preset::Hard => Preset::Custom,
+      Preset::Custom => Preset::Easy,
+    };
+    // Also writes out the custom values, for them to be changed in the file.
+    save::store(DIFFICULTY_FILE, &*difficulty);
+  }
+}
@@ -0,0 +1,333 @@
+use bevy_ecs::prelude::*;
+use macroquad::audio::play_sound_once;
+use macroquad::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+use crate::behavior::Personality;
+use crate::difficulty::{
+  Difficulty,
+  Preset,
+};
+use crate::locale::Locale;
+use crate::{
+  draw_ui_button,
+  save,
+  Cat,
+  CatKind,
+  Cheated,
+  DeltaTime,
+  GameState,
+  Obstacle,
+  ObstacleKind,
+  Outcome,
+  PlayMode,
+  Player,
+  RoundOver,
+  TextureManager,
+  Tongue,
+  TongueGoal,
+  LEVEL_HEIGHT,
+  LEVEL_WIDTH,
+  OBSTACLE_MANEKI_HEIGHT,
+  OBSTACLE_MANEKI_WIDTH,
+  TONGUE_HEIGHT,
+  TONGUE_WIDTH,
+};
+
+const ENDLESS_FIRST_WAVE: f32 = 3.0;
+const ENDLESS_WAVE_TIME: f32 = 20.0;
+const ENDLESS_MAX_CATS: usize = 12;
+const ENDLESS_MANEKI_TIME: f32 = 30.0;
+const ENDLESS_MAX_MANEKI: usize = 4;
+// Cats come in from this far outside the level.
+const ENDLESS_SPAWN_MARGIN: f32 = 200.0;
+// Random spots to pick from when spawning something, the one furthest from everything else wins.
+const ENDLESS_SPAWN_TRIES: usize = 8;
+// Every tongue is worth this much times the current wave.
+const ENDLESS_TONGUE_SCORE: usize = 100;
+const ENDLESS_HIGH_SCORES: usize = 5;
+const HIGH_SCORES_FILE: &str = "endless.ron";
+
+// The wave every kind of cat starts showing up in.
+const ENDLESS_CAT_WAVES: [(usize, CatKind); 7] = [
+  (1, CatKind::Attacker),
+  (2, CatKind::Defender),
+  (3, CatKind::Slowing),
+  (4, CatKind::Pouncer),
+  (5, CatKind::Sneak),
+  (6, CatKind::Herder),
+  (7, CatKind::Splitter),
+];
+
+#[derive(Default)]
+pub struct Endless {
+  // Set when the level select started a run instead of a level.
+  pub active: bool,
+  wave: usize,
+  wave_timer: f32,
+  maneki_timer: f32,
+  // Tongues already counted in the score.
+  caught: usize,
+  score: usize,
+  // The score of the run that just ended, for the level select to show.
+  last: Option<usize>,
+}
+
+impl Endless {
+  fn count_catches(&mut self, goal: &TongueGoal) {
+    if goal.collected > self.caught {
+      self.score += (goal.collected - self.caught) * ENDLESS_TONGUE_SCORE * self.wave.max(1);
+      self.caught = goal.collected;
+    }
+  }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct HighScore {
+  score: usize,
+  wave: usize,
+  // Scores saved before there were difficulties count as normal.
+  #[serde(default)]
+  difficulty: Preset,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct HighScores(Vec<HighScore>);
+
+impl HighScores {
+  pub fn load() -> HighScores { save::load(HIGH_SCORES_FILE) }
+
+  // Every difficulty keeps its own best scores.
+  fn record(&mut self, high_score: HighScore) {
+    self.0.push(high_score);
+    self.0.sort_by_key(|high_score| std::cmp::Reverse(high_score.score));
+    let mut kept: Vec<HighScore> = Vec::new();
+    for high_score in self.0.drain(..) {
+      let same = kept.iter().filter(|other| other.difficulty == high_score.difficulty).count();
+      if same < ENDLESS_HIGH_SCORES {
+        kept.push(high_score);
+      }
+    }
+    self.0 = kept;
+    save::store(HIGH_SCORES_FILE, self);
+  }
+
+  fn best(&self, difficulty: Preset) -> usize {
+    self
+      .0
+      .iter()
+      .find(|high_score| high_score.difficulty == difficulty)
+      .map_or(0, |high_score| high_score.score)
+  }
+}
+
+fn clearance(spot: Vec2, avoid: &[Vec2]) -> f32 {
+  avoid.iter().map(|point| point.distance(spot)).fold(f32::MAX, f32::min)
+}
+
+// Out of a few random spots, the one furthest from everything in `avoid`.
+fn pick_spot(random: fn() -> Vec2, avoid: &[Vec2]) -> Vec2 {
+  (0..ENDLESS_SPAWN_TRIES)
+    .map(|_| random())
+    .max_by(|a, b| clearance(*a, avoid).total_cmp(&clearance(*b, avoid)))
+    .unwrap()
+}
+
+fn in_level() -> Vec2 {
+  vec2(rand::gen_range(0.0, LEVEL_WIDTH), rand::gen_range(0.0, LEVEL_HEIGHT))
+}
+
+// A point on the border around the level.
+fn around_level() -> Vec2 {
+  let area = Rect::new(
+    -ENDLESS_SPAWN_MARGIN,
+    -ENDLESS_SPAWN_MARGIN,
+    LEVEL_WIDTH + ENDLESS_SPAWN_MARGIN * 2.0,
+    LEVEL_HEIGHT + ENDLESS_SPAWN_MARGIN * 2.0,
+  );
+  match rand::gen_range(0, 4) {
+    0 => vec2(rand::gen_range(area.left(), area.right()), area.top()),
+    1 => vec2(rand::gen_range(area.left(), area.right()), area.bottom()),
+    2 => vec2(area.left(), rand::gen_range(area.top(), area.bottom())),
+    _ => vec2(area.right(), rand::gen_range(area.top(), area.bottom())),
+  }
+}
+
+pub fn start_run(mut endless: ResMut<Endless>, mut goal: ResMut<TongueGoal>) {
+  if !endless.active {
+    return;
+  }
+
+  *endless = Endless {
+    active: true,
+    wave_timer: ENDLESS_FIRST_WAVE,
+    maneki_timer: ENDLESS_MANEKI_TIME,
+    ..Default::default()
+  };
+  *goal = TongueGoal { collected: 0, required: None, total: 0 };
+}
+
+// A new tongue shows up away from everyone as soon as the last one is caught.
+pub fn respawn_tongue(
+  mut commands: Commands,
+  mut endless: ResMut<Endless>,
+  goal: Res<TongueGoal>,
+  tongues: Query<&Tongue>,
+  players: Query<&Player>,
+  cats: Query<&Cat>,
+) {
+  if !endless.active || !tongues.is_empty() {
+    return;
+  }
+  endless.count_catches(&goal);
+
+  let avoid: Vec<Vec2> = players
+    .iter()
+    .map(|player| player.rect.center())
+    .chain(cats.iter().map(|cat| cat.rect.center()))
+    .collect();
+  let pos = pick_spot(in_level, &avoid) - vec2(TONGUE_WIDTH, TONGUE_HEIGHT) / 2.0;
+  let personality = match endless.wave {
+    0..=2 => Personality::Lazy,
+    3..=5 => Personality::Skittish,
+    _ => Personality::Sly,
+  };
+  commands.spawn_bundle(Tongue::new(pos, personality));
+}
+
+// Every wave brings more cats, of more kinds, from around the level.
+pub fn spawn_wave(
+  dt: Res<DeltaTime>,
+  mut commands: Commands,
+  mut endless: ResMut<Endless>,
+  players: Query<&Player>,
+  cats: Query<&Cat>,
+) {
+  if !endless.active {
+    return;
+  }
+  endless.wave_timer -= dt.0;
+  if endless.wave_timer > 0.0 {
+    return;
+  }
+  endless.wave += 1;
+  endless.wave_timer = ENDLESS_WAVE_TIME;
+
+  let kinds: Vec<CatKind> = ENDLESS_CAT_WAVES
+    .iter()
+    .filter(|(wave, _)| *wave <= endless.wave)
+    .map(|(_, kind)| *kind)
+    .collect();
+  let count = (1 + endless.wave / 2).min(ENDLESS_MAX_CATS.saturating_sub(cats.iter().count()));
+
+  let mut avoid: Vec<Vec2> = players.iter().map(|player| player.rect.center()).collect();
+  for _ in 0..count {
+    let pos = pick_spot(around_level, &avoid);
+    avoid.push(pos);
+    commands.spawn_bundle(Cat::new(pos, kinds[rand::gen_range(0, kinds.len())]));
+  }
+}
+
+pub fn spawn_maneki(
+  dt: Res<DeltaTime>,
+  mut commands: Commands,
+  mut endless: ResMut<Endless>,
+  players: Query<&Player>,
+  tongues: Query<&Tongue>,
+  cats: Query<&Cat>,
+  obstacles: Query<&Obstacle>,
+) {
+  if !endless.active {
+    return;
+  }
+  endless.maneki_timer -= dt.0;
+  if endless.maneki_timer > 0.0 {
+    return;
+  }
+  endless.maneki_timer = ENDLESS_MANEKI_TIME;
+  if obstacles.iter().count() >= ENDLESS_MAX_MANEKI {
+    return;
+  }
+
+  let avoid: Vec<Vec2> = players
+    .iter()
+    .map(|player| player.rect.center())
+    .chain(tongues.iter().map(|tongue| tongue.rect.center()))
+    .chain(cats.iter().map(|cat| cat.rect.center()))
+    .chain(obstacles.iter().map(|obstacle| obstacle.rect.center()))
+    .collect();
+  let pos = pick_spot(in_level, &avoid) - vec2(OBSTACLE_MANEKI_WIDTH, OBSTACLE_MANEKI_HEIGHT) / 2.0;
+  commands.spawn_bundle(Obstacle::new(pos, ObstacleKind::Maneki));
+}
+
+// The run is over once a cat grabs a tongue.
+pub fn finish_run(
+  round_over: Res<RoundOver>,
+  goal: Res<TongueGoal>,
+  difficulty: Res<Difficulty>,
+  cheated: Res<Cheated>,
+  mut endless: ResMut<Endless>,
+  mut high_scores: ResMut<HighScores>,
+) {
+  // The lost round is held while the grab plays, the run only gets recorded once.
+  if !endless.active || round_over.0 != Some(Outcome::Lost) || endless.last.is_some() {
+    return;
+  }
+
+  endless.count_catches(&goal);
+  endless.last = Some(endless.score);
+  if cheated.0 {
+    return;
+  }
+  high_scores.record(HighScore {
+    score: endless.score,
+    wave: endless.wave,
+    difficulty: difficulty.preset,
+  });
+}
+
+pub fn draw_run(tm: Res<TextureManager>, locale: Res<Locale>, endless: Res<Endless>) {
+  if !endless.active {
+    return;
+  }
+
+  let rect = Rect::new(10.0, 10.0, 300.0, 50.0);
+  let text = locale.format("endless.run", &[("score", &endless.score), ("wave", &endless.wave)]);
+  draw_ui_button(&tm, &rect, &text);
+}
+
+// The button starting a run and the score of the last one, next to the level select.
+pub fn endless_select(
+  tm: Res<TextureManager>,
+  locale: Res<Locale>,
+  mode: Res<PlayMode>,
+  mut game_state: ResMut<State<GameState>>,
+  mut endless: ResMut<Endless>,
+  high_scores: Res<HighScores>,
+  difficulty: Res<Difficulty>,
+) {
+  // Levels picked from the level select aren't runs.
+  endless.active = false;
+  if *mode == PlayMode::Versus {
+    return;
+  }
+
+  if let Some(score) = endless.last {
+    let rect = Rect::new(screen_width() / 2.0 - 250.0, 20.0, 500.0, 50.0);
+    let best = high_scores.best(difficulty.preset);
+    let text = locale.format("endless.over", &[("score", &score), ("best", &best)]);
+    draw_ui_button(&tm, &rect, &text);
+  }
+
+  let button = Rect::new(screen_width() / 2.0 + 10.0, screen_height() - 175.0, 240.0, 50.0);
+  draw_ui_button(&tm, &button, &locale.text("endless.button"));
+
+  if button.contains(mouse_position().into()) && is_mouse_button_pressed(MouseButton::Left) {
+    play_sound_once(tm.ui);
+    endless.active = true;
+    let _ = game_state.overwrite_set(GameState::Playing);
+  }
+}
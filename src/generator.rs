@@ -0,0 +1,429 @@
+use bevy_ecs::prelude::*;
+use macroquad::audio::play_sound_once;
+use macroquad::miniquad::date;
+use macroquad::prelude::*;
+
+use crate::behavior::Personality;
+use crate::console::Console;
+use crate::locale::Locale;
+use crate::{
+  draw_ui_button,
+  level_button,
+  Cat,
+  CatKind,
+  GameState,
+  Obstacle,
+  ObstacleKind,
+  PowerUpKind,
+  TextureManager,
+  Tongue,
+  TongueGoal,
+  ARENA_PADDING,
+  LEVEL_COUNT,
+  LEVEL_HEIGHT,
+  LEVEL_WIDTH,
+  OBSTACLE_MANEKI_HEIGHT,
+  OBSTACLE_MANEKI_WIDTH,
+  PLAYER_COOP_SPACING,
+  PLAYER_HEIGHT,
+  PLAYER_WIDTH,
+  TONGUE_HEIGHT,
+  TONGUE_WIDTH,
+};
+
+const GEN_MAX_DIFFICULTY: u32 = 10;
+const GEN_DEFAULT_DIFFICULTY: u32 = 3;
+// Layouts that don't fit are thrown away and tried again with the next numbers from the seed.
+const GEN_LAYOUT_TRIES: usize = 50;
+const GEN_SPOT_TRIES: usize = 30;
+// Space kept free around everything placed.
+const GEN_GAP: f32 = 20.0;
+const GEN_TONGUE_PLAYER_DISTANCE: f32 = 300.0;
+const GEN_CAT_PLAYER_DISTANCE: f32 = 300.0;
+const GEN_CAT_TONGUE_DISTANCE: f32 = 200.0;
+// Attackers make straight for the tongue, so they start further away from it.
+const GEN_ATTACKER_TONGUE_DISTANCE: f32 = 400.0;
+// Size of the grid the player walks on when checking that every tongue can be reached.
+const GEN_CELL: f32 = 25.0;
+
+// The difficulty every kind of cat starts showing up at.
+const GEN_CAT_KINDS: [(u32, CatKind); 7] = [
+  (1, CatKind::Attacker),
+  (2, CatKind::Defender),
+  (3, CatKind::Slowing),
+  (5, CatKind::Pouncer),
+  (6, CatKind::Sneak),
+  (7, CatKind::Herder),
+  (8, CatKind::Splitter),
+];
+
+// The generator's own random numbers, so a seed makes the same layout whatever else used the game's
+// ones. They're the numbers `rand::srand` would give for the seed.
+struct Rng(u64);
+
+impl Rng {
+  fn new(seed: u64) -> Rng {
+    let mut rng = Rng(0);
+    rng.next();
+    rng.0 = rng.0.wrapping_add(seed);
+    rng.next();
+    rng
+  }
+
+  // PCG, from 0 to `u32::MAX`.
+  fn next(&mut self) -> u32 {
+    let old = self.0;
+    self.0 = old.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    ((((old >> 18) ^ old) >> 27) as u32).rotate_right((old >> 59) as u32)
+  }
+
+  fn range(&mut self, low: f32, high: f32) -> f32 {
+    low + (high - low) * (self.next() as f32 / u32::MAX as f32)
+  }
+
+  // From 0 up to but not including `count`.
+  fn below(&mut self, count: u32) -> u32 {
+    (self.range(0.0, count as f32) as u32).min(count.saturating_sub(1))
+  }
+}
+
+// Everything a generated level starts with, positions are the top left corners like in the levels.
+#[derive(Clone, PartialEq)]
+pub struct Layout {
+  pub player: Vec2,
+  tongues: Vec<(Vec2, Personality)>,
+  cats: Vec<(Vec2, CatKind)>,
+  maneki: Vec<Vec2>,
+  // Replaces the powerups picked in the level select.
+  pub powerup: Option<PowerUpKind>,
+}
+
+pub struct Generator {
+  pub seed: u32,
+  pub difficulty: u32,
+  // Whether the seed is being typed in, the first digit typed replaces the rolled seed.
+  typing: bool,
+  // Set when the level select picked the generated level instead of one of the others.
+  pub layout: Option<Layout>,
+}
+
+impl Generator {
+  pub fn new() -> Generator {
+    Generator {
+      seed: (date::now() * 1000.0) as u64 as u32,
+      difficulty: GEN_DEFAULT_DIFFICULTY,
+      typing: false,
+      layout: None,
+    }
+  }
+}
+
+fn inflate(rect: Rect, by: Vec2) -> Rect {
+  Rect::new(rect.x - by.x, rect.y - by.y, rect.w + by.x * 2.0, rect.h + by.y * 2.0)
+}
+
+// A random free spot in the level for something of `size`, which is taken once found.
+fn place(
+  rng: &mut Rng,
+  size: Vec2,
+  placed: &mut Vec<Rect>,
+  fits: impl Fn(Rect) -> bool,
+) -> Option<Rect> {
+  let rect = (0..GEN_SPOT_TRIES)
+    .map(|_| {
+      Rect::new(
+        rng.range(0.0, LEVEL_WIDTH - size.x),
+        rng.range(0.0, LEVEL_HEIGHT - size.y),
+        size.x,
+        size.y,
+      )
+    })
+    .find(|rect| {
+      placed.iter().all(|other| !inflate(*other, Vec2::splat(GEN_GAP)).overlaps(rect))
+        && fits(*rect)
+    })?;
+  placed.push(rect);
+  Some(rect)
+}
+
+// Whether the player can walk from `start` to every tongue around the obstacles, on a grid of the
+// spots the center of the player can be at.
+fn reachable(start: Vec2, tongues: &[Rect], obstacles: &[Rect]) -> bool {
+  let arena = Rect::new(
+    -ARENA_PADDING,
+    -ARENA_PADDING,
+    LEVEL_WIDTH + ARENA_PADDING * 2.0,
+    LEVEL_HEIGHT + ARENA_PADDING * 2.0,
+  );
+  let half = vec2(PLAYER_WIDTH, PLAYER_HEIGHT) / 2.0;
+  let (columns, rows) = ((arena.w / GEN_CELL) as usize, (arena.h / GEN_CELL) as usize);
+  let center = |cell: usize| {
+    arena.point() + (vec2((cell % columns) as f32, (cell / columns) as f32) + 0.5) * GEN_CELL
+  };
+  let blocked = |pos: Vec2| obstacles.iter().any(|obstacle| inflate(*obstacle, half).contains(pos));
+
+  let first = ((start - arena.point()) / GEN_CELL).floor();
+  let first = first.y as usize * columns + first.x as usize;
+  let mut seen = vec![false; columns * rows];
+  let mut open = vec![first];
+  seen[first] = true;
+  while let Some(cell) = open.pop() {
+    let (x, y) = (cell % columns, cell / columns);
+    let neighbours = [
+      (x > 0).then(|| cell - 1),
+      (x + 1 < columns).then(|| cell + 1),
+      (y > 0).then(|| cell - columns),
+      (y + 1 < rows).then(|| cell + columns),
+    ];
+    for next in neighbours.into_iter().flatten() {
+      if !seen[next] && !blocked(center(next)) {
+        seen[next] = true;
+        open.push(next);
+      }
+    }
+  }
+
+  tongues.iter().all(|tongue| {
+    let touching = inflate(*tongue, half);
+    (0..seen.len()).any(|cell| seen[cell] && touching.contains(center(cell)))
+  })
+}
+
+// Cats that don't fit are left out unless `strict`.
+fn try_layout(rng: &mut Rng, difficulty: u32, strict: bool) -> Option<Layout> {
+  let mut placed = Vec::new();
+
+  // Room for both players in co-op, the second one spawns to the right of the first.
+  let offset = vec2(PLAYER_COOP_SPACING / 2.0, 0.0);
+  let start =
+    place(rng, vec2(PLAYER_COOP_SPACING + PLAYER_WIDTH, PLAYER_HEIGHT), &mut placed, |_| true)?;
+  let player = start.center();
+
+  let personality = match difficulty {
+    0..=3 => Personality::Lazy,
+    4..=6 => Personality::Skittish,
+    _ => Personality::Sly,
+  };
+  let tongue_count = match difficulty {
+    0..=6 => 1,
+    7..=8 => 2,
+    _ => 3,
+  };
+  let tongues = (0..tongue_count)
+    .map(|_| {
+      place(rng, vec2(TONGUE_WIDTH, TONGUE_HEIGHT), &mut placed, |rect| {
+        rect.center().distance(player) >= GEN_TONGUE_PLAYER_DISTANCE
+      })
+    })
+    .collect::<Option<Vec<Rect>>>()?;
+
+  let maneki: Vec<Rect> = (0..rng.below(difficulty / 3 + 1))
+    .filter_map(|_| {
+      place(rng, vec2(OBSTACLE_MANEKI_WIDTH, OBSTACLE_MANEKI_HEIGHT), &mut placed, |_| true)
+    })
+    .collect();
+  if !reachable(player, &tongues, &maneki) {
+    return None;
+  }
+
+  let kinds: Vec<CatKind> =
+    GEN_CAT_KINDS.iter().filter(|(from, _)| *from <= difficulty).map(|(_, kind)| *kind).collect();
+  let mut cats = Vec::new();
+  for _ in 0..difficulty.div_ceil(2) {
+    let kind = kinds[rng.below(kinds.len() as u32) as usize];
+    let tongue_distance = match kind {
+      CatKind::Attacker => GEN_ATTACKER_TONGUE_DISTANCE,
+      _ => GEN_CAT_TONGUE_DISTANCE,
+    };
+    let spot = place(rng, Cat::new(Vec2::ZERO, kind).0.rect.size(), &mut placed, |rect| {
+      rect.center().distance(player) >= GEN_CAT_PLAYER_DISTANCE
+        && tongues.iter().all(|tongue| rect.center().distance(tongue.center()) >= tongue_distance)
+    });
+    match spot {
+      Some(rect) => cats.push((rect.point(), kind)),
+      None if strict => return None,
+      None => {},
+    }
+  }
+
+  Some(Layout {
+    player: start.point() + offset,
+    tongues: tongues.iter().map(|tongue| (tongue.point(), personality)).collect(),
+    cats,
+    maneki: maneki.iter().map(|obstacle| obstacle.point()).collect(),
+    powerup: None,
+  })
+}
+
+// The same seed and difficulty always make the same layout.
+pub fn generate(seed: u32, difficulty: u32) -> Layout {
+  let mut rng = Rng::new(seed as u64);
+  (0..GEN_LAYOUT_TRIES)
+    .find_map(|_| try_layout(&mut rng, difficulty, true))
+    .or_else(|| try_layout(&mut rng, difficulty, false))
+    .unwrap_or_else(|| Layout {
+      player: Vec2::ZERO,
+      tongues: vec![(
+        vec2(LEVEL_WIDTH - TONGUE_WIDTH, LEVEL_HEIGHT - TONGUE_HEIGHT),
+        Personality::Lazy,
+      )],
+      cats: Vec::new(),
+      maneki: Vec::new(),
+      powerup: None,
+    })
+}
+
+pub fn spawn_layout(
+  mut commands: Commands,
+  generator: Res<Generator>,
+  mut goal: ResMut<TongueGoal>,
+) {
+  let Some(layout) = &generator.layout else {
+    return;
+  };
+
+  for (id, (pos, personality)) in layout.tongues.iter().enumerate() {
+    let mut tongue = Tongue::new(*pos, *personality);
+    tongue.0.id = id;
+    commands.spawn_bundle(tongue);
+  }
+  for (pos, kind) in &layout.cats {
+    commands.spawn_bundle(Cat::new(*pos, *kind));
+  }
+  for pos in &layout.maneki {
+    commands.spawn_bundle(Obstacle::new(*pos, ObstacleKind::Maneki));
+  }
+  let total = layout.tongues.len();
+  *goal = TongueGoal { collected: 0, required: Some(total), total };
+}
+
+// So it can be passed on to whoever wants to play the same level.
+pub fn draw_seed(tm: Res<TextureManager>, locale: Res<Locale>, generator: Res<Generator>) {
+  if generator.layout.is_none() {
+    return;
+  }
+
+  let rect = Rect::new(10.0, screen_height() - 60.0, 300.0, 50.0);
+  let text = locale
+    .format("generator.seed", &[("seed", &generator.seed), ("difficulty", &generator.difficulty)]);
+  draw_ui_button(&tm, &rect, &text);
+}
+
+// The seed is typed in and the difficulty picked with the arrow keys.
+fn type_seed(generator: &mut Generator) {
+  let digits = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+  ];
+  for (digit, key) in digits.into_iter().enumerate() {
+    if is_key_pressed(key) {
+      if !generator.typing {
+        generator.seed = 0;
+        generator.typing = true;
+      }
+      generator.seed = generator
+        .seed
+        .checked_mul(10)
+        .and_then(|seed| seed.checked_add(digit as u32))
+        .unwrap_or(generator.seed);
+    }
+  }
+  if is_key_pressed(KeyCode::Backspace) {
+    generator.seed /= 10;
+  }
+  if is_key_pressed(KeyCode::Left) {
+    generator.difficulty = (generator.difficulty - 1).max(1);
+  }
+  if is_key_pressed(KeyCode::Right) {
+    generator.difficulty = (generator.difficulty + 1).min(GEN_MAX_DIFFICULTY);
+  }
+}
+
+// The buttons after the last level.
+pub fn generator_select(
+  tm: Res<TextureManager>,
+  locale: Res<Locale>,
+  console: Res<Console>,
+  mut game_state: ResMut<State<GameState>>,
+  mut generator: ResMut<Generator>,
+) {
+  // Levels picked from the level select aren't generated.
+  generator.layout = None;
+
+  if !console.is_open() {
+    type_seed(&mut generator);
+  }
+
+  let play_button = level_button(LEVEL_COUNT);
+  draw_ui_button(&tm, &play_button, &locale.text("generator.play"));
+  let new_button = level_button(LEVEL_COUNT + 1);
+  draw_ui_button(&tm, &new_button, &locale.text("generator.roll"));
+
+  let text = locale.format(
+    "generator.seed_long",
+    &[("seed", &generator.seed), ("difficulty", &generator.difficulty)],
+  );
+  let text_measure = tm.fonts.measure(&text, tm.theme.font_size);
+  tm.fonts.draw(
+    &text,
+    (screen_width() - text_measure.width) / 2.0,
+    play_button.bottom() + 35.0,
+    tm.theme.font_size,
+    tm.theme.fg,
+  );
+
+  let mouse_pointer: Vec2 = mouse_position().into();
+  if new_button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
+    play_sound_once(tm.ui);
+    generator.seed = rand::rand();
+    generator.typing = false;
+  }
+  if play_button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
+    play_sound_once(tm.ui);
+    generator.layout = Some(generate(generator.seed, generator.difficulty));
+    generator.typing = false;
+    let _ = game_state.overwrite_set(GameState::Playing);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn seeds_make_the_same_layout_every_time() {
+    for difficulty in 1..=GEN_MAX_DIFFICULTY {
+      let layout = generate(1234, difficulty);
+      // The game's own random numbers don't change what the seed makes.
+      rand::srand(99);
+      assert!(generate(1234, difficulty) == layout);
+    }
+    assert!(generate(1234, 5) != generate(4321, 5));
+  }
+
+  #[test]
+  fn tongues_walled_in_by_obstacles_are_unreachable() {
+    let tongue = Rect::new(400.0, 300.0, TONGUE_WIDTH, TONGUE_HEIGHT);
+    let start = vec2(100.0, 100.0);
+    assert!(reachable(start, &[tongue], &[]));
+
+    let around = inflate(tongue, Vec2::splat(60.0));
+    let walls = [
+      Rect::new(around.x - 20.0, around.y - 20.0, around.w + 40.0, 20.0),
+      Rect::new(around.x - 20.0, around.bottom(), around.w + 40.0, 20.0),
+      Rect::new(around.x - 20.0, around.y, 20.0, around.h),
+      Rect::new(around.right(), around.y, 20.0, around.h),
+    ];
+    assert!(!reachable(start, &[tongue], &walls));
+    // A gap in the walls wide enough for the player lets it through.
+    assert!(reachable(start, &[tongue], &walls[1..]));
+  }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::assets;
+use crate::atlas::SpriteAtlas;
+use crate::locale::{
+  Fonts,
+  Locale,
+};
+use crate::TextureManager;
+
+const POLL_INTERVAL: f32 = 0.5;
+
+// Polls the asset override directory and remembers which files changed since they were loaded.
+pub struct AssetWatcher {
+  dir: Option<PathBuf>,
+  timer: f32,
+  seen: HashMap<String, SystemTime>,
+  loaded: HashMap<String, SystemTime>,
+  changed: Vec<String>,
+}
+
+impl AssetWatcher {
+  pub fn new() -> AssetWatcher {
+    let mut watcher = AssetWatcher {
+      dir: assets::override_dir(),
+      timer: POLL_INTERVAL,
+      seen: HashMap::new(),
+      loaded: HashMap::new(),
+      changed: Vec::new(),
+    };
+    watcher.seen = watcher.scan();
+    watcher.loaded = watcher.seen.clone();
+    watcher
+  }
+
+  fn scan(&self) -> HashMap<String, SystemTime> {
+    let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+      return HashMap::new();
+    };
+
+    entries
+      .filter_map(|entry| {
+        let entry = entry.ok()?;
+        let modified = entry.metadata().ok()?.modified().ok()?;
+        Some((entry.file_name().into_string().ok()?, modified))
+      })
+      .collect()
+  }
+}
+
+pub fn watch_assets(mut watcher: ResMut<AssetWatcher>) {
+  watcher.timer -= get_frame_time();
+  if watcher.timer > 0.0 {
+    return;
+  }
+  watcher.timer = POLL_INTERVAL;
+
+  let seen = watcher.scan();
+  for (name, modified) in &seen {
+    // Only reload once a file stopped changing for a whole poll, so we don't read half-written files.
+    if watcher.seen.get(name) == Some(modified) && watcher.loaded.get(name) != Some(modified) {
+      watcher.loaded.insert(name.clone(), *modified);
+      watcher.changed.push(name.clone());
+    }
+  }
+  watcher.seen = seen;
+}
+
+// Loading sounds is async, so this runs from the main loop instead of as a system.
+pub async fn reload_changed_assets(world: &mut World) {
+  let changed = std::mem::take(&mut world.resource_mut::<AssetWatcher>().changed);
+  // The language being played in is loaded again as a whole, whichever language file changed.
+  if changed.iter().any(|name| name.starts_with("lang_")) {
+    let code = world.resource::<Locale>().code.clone();
+    world.insert_resource(Locale::load(&code));
+  }
+  let mut tm = world.resource_mut::<TextureManager>();
+
+  for name in changed {
+    match tm.reload(&name).await {
+      Ok(()) => info!("Reloaded {}", name),
+      Err(err) => warn!("Failed to reload {}: {}", name, err),
+    }
+  }
+}
+
+impl TextureManager {
+  async fn reload(&mut self, name: &str) -> Result<(), String> {
+    let sound = match name {
+      "bounce_1.wav" => &mut self.bounce_1,
+      "bounce_2.wav" => &mut self.bounce_2,
+      "bounce_3.wav" => &mut self.bounce_3,
+      "lose.wav" => &mut self.lose,
+      "ui.wav" => &mut self.ui,
+      "win.wav" => &mut self.win,
+      "yoster-island.ttf" => {
+        self.yoster_island = assets::load_ttf_font(name).await?;
+        self.fonts = Fonts::load()?;
+        return Ok(());
+      },
+      // Language files list the fallback fonts.
+      _ if name.ends_with(".ttf") || name.starts_with("lang_") => {
+        self.fonts = Fonts::load()?;
+        return Ok(());
+      },
+      // Any image could be part of the atlas, so the whole thing gets rebuilt.
+      _ if name.ends_with(".png") || name == "sprites.ron" => {
+        self.atlas = SpriteAtlas::load().map_err(|err| err.to_string())?;
+        return Ok(());
+      },
+      _ => return Ok(()),
+    };
+
+    *sound = assets::load_sound(name).await?;
+    Ok(())
+  }
+}
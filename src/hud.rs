@@ -0,0 +1,272 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::camera::{
+  in_views,
+  View,
+  Views,
+};
+use crate::console::Console;
+use crate::locale::Locale;
+use crate::settings::Preferences;
+use crate::{
+  draw_ui_button,
+  Arena,
+  Cat,
+  CatKind,
+  Obstacle,
+  Player,
+  TextureManager,
+  Tongue,
+  TongueGoal,
+};
+
+const INDICATOR_MARGIN: f32 = 24.0;
+const INDICATOR_MIN_SIZE: f32 = 8.0;
+const INDICATOR_MAX_SIZE: f32 = 20.0;
+// Things further than this from the camera get the smallest arrow.
+const INDICATOR_FAR: f32 = 1200.0;
+
+const MINIMAP_WIDTH: f32 = 180.0;
+const MINIMAP_MARGIN: f32 = 10.0;
+const MINIMAP_DOT_SIZE: f32 = 6.0;
+
+const COUNTER_MARGIN: f32 = 10.0;
+const COUNTER_WIDTH: f32 = 140.0;
+const COUNTER_HEIGHT: f32 = 50.0;
+
+const SPLIT_LINE_WIDTH: f32 = 4.0;
+
+const BADGE_RADIUS: f32 = 9.0;
+const BADGE_OUTLINE: f32 = 2.0;
+const BADGE_MARGIN: f32 = 6.0;
+
+const TONGUE_COLOR: Color = color_u8!(255, 110, 150, 255);
+
+pub struct Minimap(pub bool);
+
+fn cat_color(kind: &CatKind) -> Color {
+  match kind {
+    CatKind::Attacker => color_u8!(160, 160, 170, 255),
+    CatKind::Defender => color_u8!(240, 140, 40, 255),
+    CatKind::Slowing => color_u8!(40, 40, 50, 255),
+    CatKind::Pouncer => color_u8!(230, 90, 90, 255),
+    CatKind::Sneak => color_u8!(130, 90, 180, 255),
+    CatKind::Herder => color_u8!(110, 210, 110, 255),
+    CatKind::Splitter | CatKind::Kitten => color_u8!(100, 160, 230, 255),
+  }
+}
+
+// Sneaks are as hard to spot on the HUD as they are in the world.
+fn cat_hud_color(cat: &Cat) -> Color { Color { a: cat.visibility, ..cat_color(&cat.kind) } }
+
+// The shape telling each kind of cat apart without its fur color: the number of sides, the rotation
+// in degrees and whether it's filled in.
+fn badge_shape(kind: &CatKind) -> (u8, f32, bool) {
+  match kind {
+    CatKind::Attacker => (3, -90.0, true),
+    CatKind::Defender => (4, 45.0, true),
+    CatKind::Slowing => (24, 0.0, true),
+    CatKind::Pouncer => (4, 0.0, true),
+    CatKind::Sneak => (24, 0.0, false),
+    CatKind::Herder => (5, -90.0, true),
+    CatKind::Splitter => (6, 0.0, true),
+    CatKind::Kitten => (6, 0.0, false),
+  }
+}
+
+pub fn draw_badge(kind: &CatKind, center: Vec2, alpha: f32) {
+  let (sides, rotation, filled) = badge_shape(kind);
+  let outline = Color { a: alpha, ..BLACK };
+  let fill = Color { a: alpha, ..WHITE };
+  if filled {
+    draw_poly(center.x, center.y, sides, BADGE_RADIUS + BADGE_OUTLINE, rotation, outline);
+    draw_poly(center.x, center.y, sides, BADGE_RADIUS, rotation, fill);
+  } else {
+    draw_poly_lines(
+      center.x,
+      center.y,
+      sides,
+      BADGE_RADIUS,
+      rotation,
+      BADGE_OUTLINE * 3.0,
+      outline,
+    );
+    draw_poly_lines(center.x, center.y, sides, BADGE_RADIUS, rotation, BADGE_OUTLINE, fill);
+  }
+}
+
+// Above every cat, when turned on in the settings.
+pub fn draw_cat_badges(preferences: Res<Preferences>, views: Res<Views>, cats: Query<&Cat>) {
+  if !preferences.cat_badges {
+    return;
+  }
+
+  in_views(&views, |view| {
+    for cat in &cats {
+      let rect = view.world_to_screen_rect(cat.rect);
+      let center = vec2(rect.center().x, rect.y - BADGE_RADIUS - BADGE_MARGIN);
+      // Sneaks don't give themselves away with their badge.
+      draw_badge(&cat.kind, center, cat.visibility);
+    }
+  });
+}
+
+fn draw_indicator(view: &View, rect: Rect, color: Color) {
+  let screen = view.viewport;
+  if view.world_to_screen_rect(rect).overlaps(&screen) {
+    return;
+  }
+
+  let center = screen.center();
+  let dir = view.world_to_screen(rect.center()) - center;
+  let half = screen.size() / 2.0 - INDICATOR_MARGIN;
+  let pos = center + dir * (half / dir.abs()).min_element();
+
+  let closeness = 1.0 - (rect.center().distance(view.camera.target) / INDICATOR_FAR).min(1.0);
+  let size = INDICATOR_MIN_SIZE + (INDICATOR_MAX_SIZE - INDICATOR_MIN_SIZE) * closeness;
+
+  let dir = dir.normalize();
+  let tip = pos + dir * size;
+  let left = pos - dir * size * 0.5 + dir.perp() * size * 0.7;
+  let right = pos - dir * size * 0.5 - dir.perp() * size * 0.7;
+  draw_triangle(tip, left, right, color);
+  draw_triangle_lines(tip, left, right, 2.0, BLACK);
+}
+
+// Arrows at the edge of the screen pointing to the tongue and the cats that are out of view.
+pub fn draw_offscreen_indicators(views: Res<Views>, tongues: Query<&Tongue>, cats: Query<&Cat>) {
+  in_views(&views, |view| {
+    for cat in &cats {
+      draw_indicator(view, cat.rect, cat_hud_color(cat));
+    }
+    for tongue in &tongues {
+      draw_indicator(view, tongue.rect, TONGUE_COLOR);
+    }
+  });
+}
+
+pub fn toggle_minimap(console: Res<Console>, mut minimap: ResMut<Minimap>) {
+  if is_key_pressed(KeyCode::M) && !console.is_open() {
+    minimap.0 = !minimap.0;
+  }
+}
+
+// Where the minimap sits in the corner, and how the arena maps onto it.
+struct MinimapLayout {
+  bounds: Rect,
+  camera: Camera2D,
+}
+
+impl MinimapLayout {
+  fn new(arena: Rect) -> MinimapLayout {
+    let size = vec2(MINIMAP_WIDTH, MINIMAP_WIDTH * arena.h / arena.w);
+    MinimapLayout {
+      bounds: Rect::new(screen_width() - size.x - MINIMAP_MARGIN, MINIMAP_MARGIN, size.x, size.y),
+      camera: Camera2D::from_display_rect(arena),
+    }
+  }
+
+  // A camera showing the whole arena maps it onto the screen, which then gets shrunk into the
+  // corner. Whatever left the arena stays on the edge of the minimap.
+  fn to_minimap(&self, point: Vec2) -> Vec2 {
+    let point = self.camera.world_to_screen(point) / vec2(screen_width(), screen_height())
+      * self.bounds.size()
+      + self.bounds.point();
+    point.clamp(self.bounds.point(), self.bounds.point() + self.bounds.size())
+  }
+
+  fn draw_dot(&self, point: Vec2, color: Color) {
+    let pos = (self.to_minimap(point) - MINIMAP_DOT_SIZE / 2.0)
+      .clamp(self.bounds.point(), self.bounds.point() + self.bounds.size() - MINIMAP_DOT_SIZE);
+    draw_rectangle(pos.x, pos.y, MINIMAP_DOT_SIZE, MINIMAP_DOT_SIZE, color);
+  }
+}
+
+// The minimap background and the obstacles on it, the markers get drawn over it by `draw_minimap`.
+pub fn draw_minimap_frame(
+  tm: Res<TextureManager>,
+  minimap: Res<Minimap>,
+  arena: Res<Arena>,
+  obstacles: Query<&Obstacle>,
+) {
+  if !minimap.0 {
+    return;
+  }
+
+  let layout = MinimapLayout::new(arena.0);
+  let bounds = layout.bounds;
+  draw_rectangle(bounds.x, bounds.y, bounds.w, bounds.h, Color { a: 0.7, ..tm.theme.bg });
+  draw_rectangle_lines(bounds.x, bounds.y, bounds.w, bounds.h, 2.0, tm.theme.fg);
+
+  for obstacle in &obstacles {
+    let top_left = layout.to_minimap(obstacle.rect.point());
+    let bottom_right = layout.to_minimap(obstacle.rect.point() + obstacle.rect.size());
+    let size = bottom_right - top_left;
+    draw_rectangle(top_left.x, top_left.y, size.x, size.y, tm.theme.fg);
+  }
+}
+
+pub fn draw_minimap(
+  minimap: Res<Minimap>,
+  views: Res<Views>,
+  arena: Res<Arena>,
+  players: Query<&Player>,
+  tongues: Query<&Tongue>,
+  cats: Query<&Cat>,
+) {
+  if !minimap.0 {
+    return;
+  }
+
+  let layout = MinimapLayout::new(arena.0);
+  for cat in &cats {
+    layout.draw_dot(cat.rect.center(), cat_hud_color(cat));
+  }
+  for tongue in &tongues {
+    layout.draw_dot(tongue.rect.center(), TONGUE_COLOR);
+  }
+  for player in &players {
+    layout.draw_dot(player.rect.center(), WHITE);
+  }
+
+  for view in &views.0 {
+    let view_top_left = layout.to_minimap(view.screen_to_world(view.viewport.point()));
+    let view_bottom_right =
+      layout.to_minimap(view.screen_to_world(view.viewport.point() + view.viewport.size()));
+    let view_size = view_bottom_right - view_top_left;
+    draw_rectangle_lines(view_top_left.x, view_top_left.y, view_size.x, view_size.y, 1.0, WHITE);
+  }
+}
+
+// Separates the views in split screen.
+pub fn draw_split_line(tm: Res<TextureManager>, views: Res<Views>) {
+  for view in views.0.iter().skip(1) {
+    draw_line(
+      view.viewport.x,
+      0.0,
+      view.viewport.x,
+      screen_height(),
+      SPLIT_LINE_WIDTH,
+      tm.theme.fg,
+    );
+  }
+}
+
+// Collected tongues out of the ones needed, with the total when the level doesn't need all of them.
+pub fn draw_tongue_counter(tm: Res<TextureManager>, locale: Res<Locale>, goal: Res<TongueGoal>) {
+  let Some(required) = goal.required else {
+    return;
+  };
+
+  let text = if required == goal.total {
+    locale.format("hud.tongues", &[("collected", &goal.collected), ("required", &required)])
+  } else {
+    locale.format(
+      "hud.tongues_total",
+      &[("collected", &goal.collected), ("required", &required), ("total", &goal.total)],
+    )
+  };
+  let rect = Rect::new(COUNTER_MARGIN, COUNTER_MARGIN, COUNTER_WIDTH, COUNTER_HEIGHT);
+  draw_ui_button(&tm, &rect, &text);
+}
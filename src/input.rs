@@ -0,0 +1,137 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::console::Console;
+use crate::net::NetStatus;
+use crate::PlayMode;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlayerId {
+  One,
+  Two,
+}
+
+impl PlayerId {
+  pub fn index(self) -> usize {
+    match self {
+      PlayerId::One => 0,
+      PlayerId::Two => 1,
+    }
+  }
+}
+
+struct Bindings {
+  up: KeyCode,
+  down: KeyCode,
+  left: KeyCode,
+  right: KeyCode,
+  powerup: KeyCode,
+}
+
+const PLAYER_ONE_BINDINGS: Bindings = Bindings {
+  up: KeyCode::W,
+  down: KeyCode::S,
+  left: KeyCode::A,
+  right: KeyCode::D,
+  powerup: KeyCode::Q,
+};
+
+const PLAYER_TWO_BINDINGS: Bindings = Bindings {
+  up: KeyCode::Up,
+  down: KeyCode::Down,
+  left: KeyCode::Left,
+  right: KeyCode::Right,
+  powerup: KeyCode::RightControl,
+};
+
+// Playing alone this also activates the powerup.
+const SOLO_POWERUP: KeyCode = KeyCode::P;
+
+// What a player wants to do this frame.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct PlayerInput {
+  pub x: i32,
+  pub y: i32,
+  pub powerup: bool,
+}
+
+impl PlayerInput {
+  fn read(bindings: &[&Bindings]) -> PlayerInput {
+    let down = |key: fn(&Bindings) -> KeyCode| bindings.iter().any(|b| is_key_down(key(b)));
+    PlayerInput {
+      x: down(|b| b.right) as i32 - down(|b| b.left) as i32,
+      y: down(|b| b.down) as i32 - down(|b| b.up) as i32,
+      powerup: down(|b| b.powerup),
+    }
+  }
+
+  // Packs the input into a byte to send it over the network.
+  pub fn to_bits(self) -> u8 {
+    (self.x > 0) as u8
+      | ((self.x < 0) as u8) << 1
+      | ((self.y > 0) as u8) << 2
+      | ((self.y < 0) as u8) << 3
+      | (self.powerup as u8) << 4
+  }
+
+  pub fn from_bits(bits: u8) -> PlayerInput {
+    let axis = |shift: u8| ((bits >> shift) & 1) as i32 - ((bits >> (shift + 1)) & 1) as i32;
+    PlayerInput { x: axis(0), y: axis(2), powerup: bits & (1 << 4) != 0 }
+  }
+}
+
+// Alone at a keyboard either set of bindings works.
+pub fn read_local() -> PlayerInput {
+  let mut input = PlayerInput::read(&[&PLAYER_ONE_BINDINGS, &PLAYER_TWO_BINDINGS]);
+  input.powerup |= is_key_down(SOLO_POWERUP);
+  input
+}
+
+#[derive(Default)]
+pub struct Inputs([PlayerInput; 2]);
+
+impl Inputs {
+  pub fn get(&self, id: PlayerId) -> PlayerInput { self.0[id.index()] }
+
+  pub fn set(&mut self, id: PlayerId, input: PlayerInput) { self.0[id.index()] = input; }
+}
+
+// Online the inputs are filled in by the session instead. Nobody moves while typing into the
+// console.
+pub fn read_inputs(
+  mode: Res<PlayMode>,
+  online: Option<Res<NetStatus>>,
+  console: Res<Console>,
+  mut inputs: ResMut<Inputs>,
+) {
+  if online.is_some() {
+    return;
+  }
+  if console.is_open() {
+    inputs.0 = Default::default();
+    return;
+  }
+
+  inputs.0 = if *mode != PlayMode::Solo {
+    [PlayerInput::read(&[&PLAYER_ONE_BINDINGS]), PlayerInput::read(&[&PLAYER_TWO_BINDINGS])]
+  } else {
+    [read_local(), PlayerInput::default()]
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn inputs_survive_packing_into_bits() {
+    for x in -1..=1 {
+      for y in -1..=1 {
+        for powerup in [false, true] {
+          let input = PlayerInput { x, y, powerup };
+          assert_eq!(PlayerInput::from_bits(input.to_bits()), input);
+        }
+      }
+    }
+  }
+}
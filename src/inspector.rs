@@ -0,0 +1,265 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::console::{
+  blocked_online,
+  Console,
+};
+use crate::net::NetStatus;
+use crate::time::TimeScale;
+use crate::{
+  draw_ui_button,
+  Cat,
+  Cheated,
+  Obstacle,
+  Pathfinder,
+  Player,
+  TextureManager,
+  Tongue,
+};
+
+const INSPECTOR_WIDTH: f32 = 380.0;
+const INSPECTOR_MARGIN: f32 = 10.0;
+const INSPECTOR_FONT_SIZE: u16 = 20;
+const INSPECTOR_LINE_HEIGHT: f32 = 22.0;
+const INSPECTOR_BUTTON_HEIGHT: f32 = 40.0;
+const INSPECTOR_SCROLL_SPEED: f32 = 40.0;
+const INSPECTOR_EDIT_COLOR: Color = color_u8!(255, 255, 0, 255);
+
+const EDIT_KEYS: [(KeyCode, char); 12] = [
+  (KeyCode::Key0, '0'),
+  (KeyCode::Key1, '1'),
+  (KeyCode::Key2, '2'),
+  (KeyCode::Key3, '3'),
+  (KeyCode::Key4, '4'),
+  (KeyCode::Key5, '5'),
+  (KeyCode::Key6, '6'),
+  (KeyCode::Key7, '7'),
+  (KeyCode::Key8, '8'),
+  (KeyCode::Key9, '9'),
+  (KeyCode::Minus, '-'),
+  (KeyCode::Period, '.'),
+];
+
+#[derive(Default)]
+pub struct Inspector {
+  open: bool,
+  scroll: f32,
+  // The field being typed into, and what was typed so far.
+  editing: Option<(Entity, &'static str, String)>,
+}
+
+enum Field<'a> {
+  Number(&'a mut f32),
+  Text(String),
+}
+
+fn number_or_none(value: &mut Option<f32>) -> Field<'_> {
+  match value {
+    Some(value) => Field::Number(value),
+    None => Field::Text("none".to_string()),
+  }
+}
+
+fn player_fields(player: &mut Player) -> Vec<(&'static str, Field<'_>)> {
+  vec![
+    ("player.x", Field::Number(&mut player.rect.x)),
+    ("player.y", Field::Number(&mut player.rect.y)),
+    ("player.dir_x", Field::Number(&mut player.dir_x)),
+    ("player.stun_timer", Field::Number(&mut player.stun_timer)),
+    ("player.powerup_timer", Field::Number(&mut player.powerup_timer)),
+    ("player.powerup_cooldown_timer", Field::Number(&mut player.powerup_cooldown_timer)),
+    ("player.speed_mul", Field::Number(&mut player.speed_mul)),
+    ("player.bounce_percentage", number_or_none(&mut player.bounce_percentage)),
+  ]
+}
+
+fn cat_fields(cat: &mut Cat) -> Vec<(&'static str, Field<'_>)> {
+  vec![
+    ("cat.x", Field::Number(&mut cat.rect.x)),
+    ("cat.y", Field::Number(&mut cat.rect.y)),
+    ("cat.dir_x", Field::Number(&mut cat.dir_x)),
+    ("cat.speed_mul", Field::Number(&mut cat.speed_mul)),
+    ("cat.bounce_percentage", number_or_none(&mut cat.bounce_percentage)),
+    ("cat.visibility", Field::Number(&mut cat.visibility)),
+    ("cat.target", Field::Text(format!("{:?}", cat.target))),
+  ]
+}
+
+fn tongue_fields(tongue: &mut Tongue) -> Vec<(&'static str, Field<'_>)> {
+  vec![
+    ("tongue.id", Field::Text(tongue.id.to_string())),
+    ("tongue.x", Field::Number(&mut tongue.rect.x)),
+    ("tongue.y", Field::Number(&mut tongue.rect.y)),
+    ("tongue.dir_x", Field::Number(&mut tongue.dir_x)),
+  ]
+}
+
+fn obstacle_fields(obstacle: &mut Obstacle) -> Vec<(&'static str, Field<'_>)> {
+  vec![
+    ("obstacle.x", Field::Number(&mut obstacle.rect.x)),
+    ("obstacle.y", Field::Number(&mut obstacle.rect.y)),
+  ]
+}
+
+fn pathfinder_fields(pathfinder: &Pathfinder) -> Vec<(&'static str, Field<'_>)> {
+  let dest =
+    pathfinder.dest.map_or("none".to_string(), |dest| format!("{:.0}, {:.0}", dest.x, dest.y));
+  vec![("pathfinder.dest", Field::Text(dest))]
+}
+
+pub fn toggle_inspector(mut inspector: ResMut<Inspector>) {
+  if is_key_pressed(KeyCode::F4) {
+    *inspector = Inspector { open: !inspector.open, ..Default::default() };
+  }
+}
+
+type Inspected<'a> = (
+  Entity,
+  Option<&'a mut Player>,
+  Option<&'a mut Cat>,
+  Option<&'a mut Tongue>,
+  Option<&'a mut Obstacle>,
+  Option<&'a Pathfinder>,
+);
+
+// Typing into a field, `Enter` keeps the value and `Escape` drops it.
+fn edit(editing: &mut String) -> Option<bool> {
+  for (key, character) in EDIT_KEYS {
+    if is_key_pressed(key) {
+      editing.push(character);
+    }
+  }
+  if is_key_pressed(KeyCode::Backspace) {
+    editing.pop();
+  }
+  if is_key_pressed(KeyCode::Enter) {
+    return Some(true);
+  }
+  if is_key_pressed(KeyCode::Escape) {
+    return Some(false);
+  }
+  None
+}
+
+// Lists every entity with the fields of its components, numbers get typed into after clicking them.
+pub fn draw_inspector(
+  tm: Res<TextureManager>,
+  console: Res<Console>,
+  mut inspector: ResMut<Inspector>,
+  mut time_scale: ResMut<TimeScale>,
+  mut entities: Query<Inspected>,
+  online: Option<Res<NetStatus>>,
+  mut cheated: ResMut<Cheated>,
+) {
+  if !inspector.open {
+    return;
+  }
+
+  let panel = Rect::new(screen_width() - INSPECTOR_WIDTH, 0.0, INSPECTOR_WIDTH, screen_height());
+  draw_rectangle(panel.x, panel.y, panel.w, panel.h, Color { a: 0.9, ..tm.theme.bg });
+
+  let mouse_pointer: Vec2 = mouse_position().into();
+  let clicked = is_mouse_button_pressed(MouseButton::Left);
+  let button_width = (INSPECTOR_WIDTH - INSPECTOR_MARGIN * 3.0) / 2.0;
+  let pause_button =
+    Rect::new(panel.x + INSPECTOR_MARGIN, INSPECTOR_MARGIN, button_width, INSPECTOR_BUTTON_HEIGHT);
+  let step_button = Rect::new(
+    pause_button.right() + INSPECTOR_MARGIN,
+    INSPECTOR_MARGIN,
+    button_width,
+    INSPECTOR_BUTTON_HEIGHT,
+  );
+  draw_ui_button(&tm, &pause_button, if time_scale.paused() { "Resume" } else { "Pause" });
+  draw_ui_button(&tm, &step_button, "Step");
+  if clicked && pause_button.contains(mouse_pointer) {
+    time_scale.toggle_pause();
+    cheated.0 = true;
+  }
+  if clicked && step_button.contains(mouse_pointer) {
+    time_scale.step();
+    cheated.0 = true;
+  }
+
+  if panel.contains(mouse_pointer) {
+    inspector.scroll =
+      (inspector.scroll - mouse_wheel().1.clamp(-1.0, 1.0) * INSPECTOR_SCROLL_SPEED).max(0.0);
+  }
+  let finished = match &mut inspector.editing {
+    Some((.., text)) if !console.is_open() => edit(text),
+    _ => None,
+  };
+
+  let params = TextParams {
+    font: tm.yoster_island,
+    font_size: INSPECTOR_FONT_SIZE,
+    color: tm.theme.fg,
+    ..Default::default()
+  };
+  let top = step_button.bottom() + INSPECTOR_MARGIN;
+  let mut y = top - inspector.scroll;
+  let mut line = |text: &str, color: Color| {
+    y += INSPECTOR_LINE_HEIGHT;
+    if y > top + INSPECTOR_LINE_HEIGHT && y < screen_height() {
+      draw_text_ex(text, panel.x + INSPECTOR_MARGIN, y, TextParams { color, ..params });
+    }
+    Rect::new(panel.x, y - INSPECTOR_LINE_HEIGHT, INSPECTOR_WIDTH, INSPECTOR_LINE_HEIGHT)
+  };
+
+  let editable = !blocked_online(online.as_deref());
+  let mut clicked_field = None;
+  for (entity, mut player, mut cat, mut tongue, mut obstacle, pathfinder) in &mut entities {
+    line(&format!("Entity {}", entity.id()), WHITE);
+
+    let mut fields = Vec::new();
+    if let Some(player) = &mut player {
+      fields.extend(player_fields(player));
+    }
+    if let Some(cat) = &mut cat {
+      fields.extend(cat_fields(cat));
+    }
+    if let Some(tongue) = &mut tongue {
+      fields.extend(tongue_fields(tongue));
+    }
+    if let Some(obstacle) = &mut obstacle {
+      fields.extend(obstacle_fields(obstacle));
+    }
+    if let Some(pathfinder) = pathfinder {
+      fields.extend(pathfinder_fields(pathfinder));
+    }
+
+    for (name, field) in fields {
+      let editing = match &inspector.editing {
+        Some((other, other_name, text)) if *other == entity && *other_name == name => Some(text),
+        _ => None,
+      };
+      let rect = match (&field, editing) {
+        (_, Some(text)) => line(&format!("  {}: {}_", name, text), INSPECTOR_EDIT_COLOR),
+        (Field::Number(value), None) => line(&format!("  {}: {:.2}", name, value), tm.theme.fg),
+        (Field::Text(text), None) => line(&format!("  {}: {}", name, text), tm.theme.fg),
+      };
+
+      if let Field::Number(value) = field {
+        match editing {
+          Some(text) => {
+            if let (Some(true), Ok(typed)) = (finished, text.parse()) {
+              *value = typed;
+              cheated.0 = true;
+            }
+          },
+          None if clicked && rect.contains(mouse_pointer) && rect.y > top && editable => {
+            clicked_field = Some((entity, name));
+          },
+          None => {},
+        }
+      }
+    }
+  }
+
+  if finished.is_some() {
+    inspector.editing = None;
+  }
+  if let Some((entity, name)) = clicked_field {
+    inspector.editing = Some((entity, name, String::new()));
+  }
+}
@@ -0,0 +1,280 @@
+use std::collections::{
+  HashMap,
+  HashSet,
+};
+use std::fmt::Display;
+
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::assets;
+
+const DEFAULT_LANGUAGE: &str = "en";
+const LANGUAGE_PREFIX: &str = "lang_";
+const LANGUAGE_SUFFIX: &str = ".ron";
+const MAIN_FONT: &str = "yoster-island.ttf";
+
+// Which plural forms a language has, following the CLDR names of the categories.
+#[derive(Clone, Copy, Default, Deserialize)]
+enum PluralRule {
+  // English, German and most other European languages.
+  #[default]
+  OneOther,
+  // Russian, Ukrainian and other East Slavic languages.
+  OneFewMany,
+  // Chinese, Japanese, Korean and other languages that don't inflect for number.
+  OtherOnly,
+}
+
+impl PluralRule {
+  fn category(self, count: usize) -> &'static str {
+    match self {
+      PluralRule::OneOther if count == 1 => "one",
+      PluralRule::OneFewMany if count % 10 == 1 && count % 100 != 11 => "one",
+      PluralRule::OneFewMany
+        if (2..=4).contains(&(count % 10)) && !(12..=14).contains(&(count % 100)) =>
+      {
+        "few"
+      },
+      PluralRule::OneFewMany => "many",
+      _ => "other",
+    }
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Message {
+  Text(String),
+  // Forms the language doesn't need can be left out, `other` is used for whatever is missing.
+  Plural {
+    #[serde(default)]
+    one: Option<String>,
+    #[serde(default)]
+    few: Option<String>,
+    #[serde(default)]
+    many: Option<String>,
+    other: String,
+  },
+}
+
+impl Message {
+  fn form(&self, category: &str) -> &str {
+    match self {
+      Message::Text(text) => text,
+      Message::Plural { one, few, many, other } => match category {
+        "one" => one.as_ref(),
+        "few" => few.as_ref(),
+        "many" => many.as_ref(),
+        _ => None,
+      }
+      .unwrap_or(other),
+    }
+  }
+}
+
+// A `lang_<code>.ron` file, new languages are added by dropping one into `mods/`.
+#[derive(Deserialize)]
+struct Language {
+  // In the language itself, for the language selector.
+  name: String,
+  #[serde(default)]
+  plurals: PluralRule,
+  // Fonts for the characters Yoster Island doesn't have, tried in order.
+  #[serde(default)]
+  fonts: Vec<String>,
+  strings: HashMap<String, Message>,
+}
+
+impl Language {
+  fn read(code: &str) -> Result<Language, String> {
+    let name = format!("{}{}{}", LANGUAGE_PREFIX, code, LANGUAGE_SUFFIX);
+    assets::load_with(&name, |bytes| {
+      let text = std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+      ron::from_str(text).map_err(|err| format!("{}: {}", name, err))
+    })
+  }
+
+  fn empty() -> Language {
+    Language {
+      name: DEFAULT_LANGUAGE.to_string(),
+      plurals: PluralRule::default(),
+      fonts: Vec::new(),
+      strings: HashMap::new(),
+    }
+  }
+}
+
+// The codes of every language there's a file for, embedded or modded.
+fn codes() -> Vec<String> {
+  let mut codes: Vec<String> = assets::names()
+    .iter()
+    .filter_map(|name| name.strip_prefix(LANGUAGE_PREFIX)?.strip_suffix(LANGUAGE_SUFFIX))
+    .map(|code| code.to_string())
+    .collect();
+  codes.sort();
+  codes.dedup();
+  codes
+}
+
+// The language after `code`, for the selector to cycle through.
+pub fn next_language(code: &str) -> String {
+  let codes = codes();
+  let index = codes.iter().position(|other| other == code).map_or(0, |index| index + 1);
+  codes.get(index % codes.len().max(1)).cloned().unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+// The language the system is set to, if there's a file for it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn system_language() -> Option<String> {
+  let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+    .iter()
+    .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))?;
+  let code = value.split(['_', '.', '@']).next()?.to_lowercase();
+  codes().contains(&code).then_some(code)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn system_language() -> Option<String> { None }
+
+// The strings of the language being played in, with English filling in whatever it's missing.
+pub struct Locale {
+  pub code: String,
+  language: Language,
+  fallback: Language,
+}
+
+impl Locale {
+  pub fn load(code: &str) -> Locale {
+    let fallback = Language::read(DEFAULT_LANGUAGE).unwrap_or_else(|err| {
+      warn!("Failed to load the default language: {}", err);
+      Language::empty()
+    });
+    match Language::read(code) {
+      Ok(language) => Locale { code: code.to_string(), language, fallback },
+      Err(err) => {
+        warn!("Failed to load language `{}`: {}", code, err);
+        Locale { code: DEFAULT_LANGUAGE.to_string(), language: Language::empty(), fallback }
+      },
+    }
+  }
+
+  pub fn name(&self) -> &str {
+    if self.language.strings.is_empty() {
+      &self.fallback.name
+    } else {
+      &self.language.name
+    }
+  }
+
+  fn message<'a>(&'a self, id: &'a str, count: usize) -> &'a str {
+    [&self.language, &self.fallback]
+      .into_iter()
+      .find_map(|language| {
+        let message = language.strings.get(id)?;
+        Some(message.form(language.plurals.category(count)))
+      })
+      .unwrap_or(id)
+  }
+
+  pub fn text(&self, id: &str) -> String { self.message(id, 0).to_string() }
+
+  // Fills in the `{name}` placeholders of the message.
+  pub fn format(&self, id: &str, args: &[(&str, &dyn Display)]) -> String {
+    fill(self.message(id, 0), args)
+  }
+
+  // Picks the plural form for `count`, which is filled in as `{count}` along with the rest.
+  pub fn plural(&self, id: &str, count: usize, args: &[(&str, &dyn Display)]) -> String {
+    fill(self.message(id, count), args).replace("{count}", &count.to_string())
+  }
+}
+
+fn fill(message: &str, args: &[(&str, &dyn Display)]) -> String {
+  args.iter().fold(message.to_string(), |text, (name, value)| {
+    text.replace(&format!("{{{}}}", name), &value.to_string())
+  })
+}
+
+// Yoster Island and the fonts drawing the characters it doesn't have, every character is drawn with
+// the first font that has it.
+pub struct Fonts(Vec<(Font, fontdue::Font)>);
+
+impl Fonts {
+  fn load_font(name: &str) -> Result<(Font, fontdue::Font), String> {
+    assets::load_with(name, |bytes| {
+      let font = load_ttf_font_from_bytes(bytes).map_err(|err| err.to_string())?;
+      let glyphs = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+        .map_err(|err| err.to_string())?;
+      Ok((font, glyphs))
+    })
+  }
+
+  // The fallback fonts of every language get loaded, so switching languages doesn't need a reload.
+  pub fn load() -> Result<Fonts, String> {
+    let mut fallbacks: Vec<String> =
+      codes().iter().filter_map(|code| Language::read(code).ok()).flat_map(|l| l.fonts).collect();
+    // Languages can share a fallback, it only gets loaded once, in the order they list it.
+    let mut seen = HashSet::new();
+    fallbacks.retain(|name| seen.insert(name.clone()));
+
+    let mut fonts = vec![Fonts::load_font(MAIN_FONT)?];
+    for name in fallbacks {
+      match Fonts::load_font(&name) {
+        Ok(font) => fonts.push(font),
+        Err(err) => warn!("Failed to load font {}: {}", name, err),
+      }
+    }
+    Ok(Fonts(fonts))
+  }
+
+  // Splits the text into pieces drawn with the same font.
+  fn runs<'a>(&self, text: &'a str) -> Vec<(Font, &'a str)> {
+    let font_of = |character: char| {
+      self
+        .0
+        .iter()
+        .find(|(_, glyphs)| glyphs.lookup_glyph_index(character) != 0)
+        .map_or(self.0[0].0, |(font, _)| *font)
+    };
+
+    let mut runs: Vec<(Font, &str)> = Vec::new();
+    let mut start = 0;
+    let mut current = None;
+    for (index, character) in text.char_indices() {
+      // Spaces are in every font, they don't start a new run.
+      let font = if character == ' ' { current } else { Some(font_of(character)) };
+      if let (Some(previous), Some(font)) = (current, font) {
+        if previous != font {
+          runs.push((previous, &text[start..index]));
+          start = index;
+        }
+      }
+      current = current.or(font).map(|previous| font.unwrap_or(previous));
+    }
+    runs.push((current.unwrap_or(self.0[0].0), &text[start..]));
+    runs
+  }
+
+  pub fn measure(&self, text: &str, font_size: u16) -> TextDimensions {
+    self.runs(text).into_iter().fold(
+      TextDimensions { width: 0.0, height: 0.0, offset_y: 0.0 },
+      |total, (font, run)| {
+        let run = measure_text(run, Some(font), font_size, 1.0);
+        TextDimensions {
+          width: total.width + run.width,
+          height: total.height.max(run.height),
+          offset_y: total.offset_y.max(run.offset_y),
+        }
+      },
+    )
+  }
+
+  pub fn draw(&self, text: &str, x: f32, y: f32, font_size: u16, color: Color) {
+    let mut x = x;
+    for (font, run) in self.runs(text) {
+      draw_text_ex(run, x, y, TextParams { font, font_size, color, ..Default::default() });
+      x += measure_text(run, Some(font), font_size, 1.0).width;
+    }
+  }
+}
@@ -1,8 +1,34 @@
 #![windows_subsystem = "windows"]
 
+mod animation;
+mod assets;
+mod atlas;
+mod behavior;
+mod camera;
+mod console;
+mod daily;
+mod debug;
+mod difficulty;
+mod endless;
+mod generator;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod hud;
+mod input;
+mod inspector;
+mod locale;
+mod net;
+mod particles;
+mod save;
+mod settings;
+mod time;
+mod tunables;
+mod versus;
+
+use std::collections::HashSet;
+
 use bevy_ecs::prelude::*;
 use macroquad::audio::{
-  load_sound,
   play_sound,
   play_sound_once,
   PlaySoundParams,
@@ -11,6 +37,77 @@ use macroquad::audio::{
 use macroquad::miniquad::conf::Icon;
 use macroquad::prelude::*;
 
+use crate::animation::{
+  Animator,
+  Clip,
+};
+use crate::atlas::SpriteAtlas;
+use crate::behavior::{
+  Brain,
+  CatState,
+  Condition,
+  Personality,
+  Senses,
+  Threat,
+  TongueBrain,
+};
+use crate::camera::{
+  in_views,
+  CameraController,
+  Views,
+};
+use crate::console::{
+  arg,
+  complete_from,
+  no_completion,
+  require_playing,
+  Command,
+  Console,
+};
+use crate::daily::{
+  Daily,
+  DailyHistory,
+};
+use crate::difficulty::Difficulty;
+use crate::endless::{
+  Endless,
+  HighScores,
+};
+use crate::generator::Generator;
+use crate::input::{
+  Inputs,
+  PlayerId,
+};
+use crate::inspector::Inspector;
+use crate::locale::{
+  Fonts,
+  Locale,
+};
+use crate::net::{
+  NetStatus,
+  Session,
+  NET_USAGE,
+};
+use crate::particles::{
+  Particles,
+  BOUNCE_IMPACT,
+  POWERUP_ACTIVATE,
+  TONGUE_CAPTURE,
+};
+use crate::settings::{
+  Preferences,
+  Theme,
+};
+use crate::time::TimeScale;
+use crate::tunables::{
+  BaseTunables,
+  Tunables,
+};
+use crate::versus::{
+  Score,
+  VersusCat,
+};
+
 fn window_conf() -> Conf {
   Conf {
     window_title: "Game Off 2022".to_string(),
@@ -25,20 +122,15 @@ fn window_conf() -> Conf {
 
 const PLAYER_WIDTH: f32 = 86.0;
 const PLAYER_HEIGHT: f32 = 105.0;
-const PLAYER_SPEED: f32 = 160.0;
-const PLAYER_SPEED_UP_TIME: f32 = 2.0;
-const PLAYER_SPEED_UP_MUL: f32 = 1.6;
-const PLAYER_NO_BOUNCE_TIME: f32 = 6.0;
-const PLAYER_BOUNCIER_TIME: f32 = 10.0;
-const PLAYER_BOUNCIER_MUL: f32 = 3.0;
-const PLAYER_POWERUP_COOLDOWN: f32 = 6.0;
+// Space between the two players when they spawn in co-op.
+const PLAYER_COOP_SPACING: f32 = 150.0;
+const PLAYER_TWO_TINT: Color = color_u8!(170, 210, 255, 255);
 const FIX_COLLISION: f32 = 5.0;
-const PLAYER_ANIMATION_FPS: f32 = 1.0 / 4.0;
 
 const TONGUE_WIDTH: f32 = 82.0;
 const TONGUE_HEIGHT: f32 = 61.0;
-const TONGUE_SPEED: f32 = 120.0;
-const TONGUE_MAX_DEST: f32 = 120.0;
+// Number of directions a fleeing tongue considers.
+const TONGUE_DIRECTIONS: usize = 16;
 
 const CAT_ATTACKER_WIDTH: f32 = 113.0;
 const CAT_ATTACKER_HEIGHT: f32 = 105.0;
@@ -46,18 +138,26 @@ const CAT_DEFENDER_WIDTH: f32 = 120.0;
 const CAT_DEFENDER_HEIGHT: f32 = 104.0;
 const CAT_SLOWING_WIDTH: f32 = 116.0;
 const CAT_SLOWING_HEIGHT: f32 = 104.0;
-const CAT_SPEED: f32 = 140.0;
-const CAT_DEFENDER_PROXIMITY: f32 = 152.0;
-const CAT_SLOWING_PROXIMITY: f32 = 224.0;
-const CAT_MAX_DEST: f32 = 140.0;
-const CAT_ATTACKER_BOUNCE: f32 = 75.0;
-const CAT_DEFENDER_BOUNCE: f32 = 152.0;
-const CAT_SLOWING_BOUNCE: f32 = 35.0;
-const CAT_SLOWING_MUL: f32 = 0.75;
+const CAT_POUNCER_WIDTH: f32 = 113.0;
+const CAT_POUNCER_HEIGHT: f32 = 105.0;
+const CAT_SNEAK_WIDTH: f32 = 113.0;
+const CAT_SNEAK_HEIGHT: f32 = 105.0;
+const CAT_HERDER_WIDTH: f32 = 120.0;
+const CAT_HERDER_HEIGHT: f32 = 104.0;
+const CAT_SPLITTER_WIDTH: f32 = 113.0;
+const CAT_SPLITTER_HEIGHT: f32 = 105.0;
+const CAT_KITTEN_WIDTH: f32 = 57.0;
+const CAT_KITTEN_HEIGHT: f32 = 53.0;
 
 const OBSTACLE_MANEKI_WIDTH: f32 = 78.0;
 const OBSTACLE_MANEKI_HEIGHT: f32 = 115.0;
-const OBSTACLE_MANEKI_PROXIMITY: f32 = 192.0;
+
+// The levels are laid out in this area, the camera and the minimap show the arena a bit further out.
+// Nothing is kept inside the arena, the camera goes past it to follow a player who left it.
+const LEVEL_COUNT: usize = 18;
+const LEVEL_WIDTH: f32 = 800.0;
+const LEVEL_HEIGHT: f32 = 600.0;
+const ARENA_PADDING: f32 = 500.0;
 
 const FONT_SIZE: u16 = 30;
 const UI_BG_COLOR: Color = color_u8!(0, 153, 219, 255);
@@ -67,40 +167,99 @@ const FLASH_DURATION: f32 = 0.25;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum GameState {
+  // Waiting for the other player of an online match.
+  Connecting,
   MainMenu,
+  Settings,
   LevelSelect,
   Playing,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlayMode {
+  Solo,
+  Coop,
+  // The second player drives a cat against the first.
+  Versus,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Outcome {
+  Won,
+  Lost,
+}
+
 struct Exit(bool);
 struct JustPressedBackButton(bool, f32);
 struct Level(usize);
 struct Flash(f32);
+// Set when the tongues are collected or a cat grabs one.
+#[derive(Clone, Hash)]
+struct RoundOver(Option<Outcome>);
+struct Arena(Rect);
+// The powerup every player picked in the level select.
+struct PowerUps([PowerUpKind; 2]);
+#[derive(Clone, Hash)]
+struct TongueGoal {
+  collected: usize,
+  // `None` when collecting tongues never wins the round.
+  required: Option<usize>,
+  total: usize,
+}
+// Seconds covered by this tick of the simulation, the frame time offline and a fixed step online.
+struct DeltaTime(f32);
+// Set while cats can't grab tongues.
+struct God(bool);
+// Set once a console command, the inspector or the time keys changed the game, results aren't saved
+// from then on.
+struct Cheated(bool);
 
 struct TextureManager {
+  atlas: SpriteAtlas,
   bounce_1: Sound,
   bounce_2: Sound,
   bounce_3: Sound,
-  bouncier_powerup: Texture2D,
-  cat_black: Texture2D,
-  cat_grey: Texture2D,
-  cat_orange: Texture2D,
-  cgyt: Texture2D,
-  cobblestone: Texture2D,
   lose: Sound,
-  manekineko: Texture2D,
-  no_bounce_powerup: Texture2D,
-  skull_closed: Texture2D,
-  skull_open: Texture2D,
-  speed_up_powerup: Texture2D,
-  tongue: Texture2D,
   ui: Sound,
   win: Sound,
   yoster_island: Font,
+  // What the UI is written with, Yoster Island and fallbacks for the letters it doesn't have.
+  fonts: Fonts,
+  theme: Theme,
+  // Set while rolled back ticks are simulated again, so their sounds don't play twice.
+  muted: bool,
+}
+
+impl TextureManager {
+  // Broken mods fall back to the built-in assets, so only those can fail here.
+  async fn load(theme: Theme) -> TextureManager {
+    TextureManager {
+      atlas: SpriteAtlas::load().unwrap(),
+      bounce_1: assets::load_sound("bounce_1.wav").await.unwrap(),
+      bounce_2: assets::load_sound("bounce_2.wav").await.unwrap(),
+      bounce_3: assets::load_sound("bounce_3.wav").await.unwrap(),
+      lose: assets::load_sound("lose.wav").await.unwrap(),
+      ui: assets::load_sound("ui.wav").await.unwrap(),
+      win: assets::load_sound("win.wav").await.unwrap(),
+      yoster_island: assets::load_ttf_font("yoster-island.ttf").await.unwrap(),
+      fonts: Fonts::load().unwrap(),
+      theme,
+      muted: false,
+    }
+  }
+
+  fn play(&self, sound: Sound) {
+    if !self.muted {
+      play_sound_once(sound);
+    }
+  }
 }
 
-#[derive(Component)]
-struct Pathfinder {}
+#[derive(Component, Clone, Default)]
+struct Pathfinder {
+  // Where the entity was last headed, `None` once it stopped.
+  dest: Option<Vec2>,
+}
 
 impl Pathfinder {
   fn update_pos(
@@ -110,11 +269,13 @@ impl Pathfinder {
     end: Vec2,
     obstacles: &Query<&Obstacle>,
     bouncing: bool,
+    dt: f32,
   ) {
+    self.dest = Some(end);
     let dir = (end - start.point()).normalize_or_zero();
 
-    start.x += speed * dir.x * get_frame_time();
-    start.y += speed * dir.y * get_frame_time();
+    start.x += speed * dir.x * dt;
+    start.y += speed * dir.y * dt;
 
     if dir.x != 0.0 && dir.y != 0.0 && !bouncing {
       for obstacle in obstacles {
@@ -123,54 +284,60 @@ impl Pathfinder {
         while let Some(intersection) = start.intersect(obstacle.rect) {
           intersectioned = (intersection.w, intersection.h);
           if intersection.w > 0.0 {
-            start.x -= FIX_COLLISION * dir.x * get_frame_time();
+            start.x -= FIX_COLLISION * dir.x * dt;
           }
           if intersection.h > 0.0 {
-            start.y -= FIX_COLLISION * dir.y * get_frame_time();
+            start.y -= FIX_COLLISION * dir.y * dt;
           }
         }
 
         if (intersectioned.0 - intersectioned.1).abs() < f32::EPSILON {
         } else if intersectioned.0 > intersectioned.1 {
-          start.y -= speed * dir.y * get_frame_time();
+          start.y -= speed * dir.y * dt;
         } else {
-          start.x -= speed * dir.x * get_frame_time();
+          start.x -= speed * dir.x * dt;
         }
       }
     }
   }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct Player {
+  id: PlayerId,
   rect: Rect,
   dir_x: f32,
   stun_timer: f32,
   powerup_timer: f32,
   powerup_kind: PowerUpKind,
   powerup_cooldown_timer: f32,
-  animation_timer: f32,
-  current_frame: usize,
   speed_mul: f32,
   bounce_dest: Vec2,
   bounce_percentage: Option<f32>,
 }
 
 impl Player {
-  fn new(pos: Vec2, powerup_kind: PowerUpKind) -> (Player,) {
-    (Player {
-      rect: Rect::new(pos.x, pos.y, PLAYER_WIDTH, PLAYER_HEIGHT),
-      dir_x: 0.0,
-      stun_timer: 0.0,
-      powerup_timer: 0.0,
-      powerup_kind,
-      powerup_cooldown_timer: 0.0,
-      animation_timer: PLAYER_ANIMATION_FPS,
-      current_frame: 0,
-      speed_mul: 1.0,
-      bounce_dest: Vec2::ZERO,
-      bounce_percentage: None,
-    },)
+  fn new(pos: Vec2, id: PlayerId, powerup_kind: PowerUpKind) -> (Player, Animator) {
+    let mut animator = Animator::new("skull");
+    if id == PlayerId::Two {
+      animator.color = PLAYER_TWO_TINT;
+    }
+
+    (
+      Player {
+        id,
+        rect: Rect::new(pos.x, pos.y, PLAYER_WIDTH, PLAYER_HEIGHT),
+        dir_x: 0.0,
+        stun_timer: 0.0,
+        powerup_timer: 0.0,
+        powerup_kind,
+        powerup_cooldown_timer: 0.0,
+        speed_mul: 1.0,
+        bounce_dest: Vec2::ZERO,
+        bounce_percentage: None,
+      },
+      animator,
+    )
   }
 }
 
@@ -181,22 +348,26 @@ enum PowerUpKind {
   Bouncier,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct Tongue {
   rect: Rect,
   dir_x: f32,
+  // Spawn order within the level, lets cats be assigned to a tongue.
+  id: usize,
 }
 
 impl Tongue {
-  fn new(pos: Vec2) -> (Tongue, Pathfinder) {
+  fn new(pos: Vec2, personality: Personality) -> (Tongue, Pathfinder, Animator, TongueBrain) {
     (
-      Tongue { rect: Rect::new(pos.x, pos.y, TONGUE_WIDTH, TONGUE_HEIGHT), dir_x: 0.0 },
-      Pathfinder {},
+      Tongue { rect: Rect::new(pos.x, pos.y, TONGUE_WIDTH, TONGUE_HEIGHT), dir_x: 0.0, id: 0 },
+      Pathfinder::default(),
+      Animator::new("tongue"),
+      TongueBrain::new(personality),
     )
   }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct Cat {
   rect: Rect,
   dir_x: f32,
@@ -204,102 +375,209 @@ struct Cat {
   speed_mul: f32,
   bounce_dest: Vec2,
   bounce_percentage: Option<f32>,
+  // How visible the cat is, only sneaks fade out.
+  visibility: f32,
+  // The id of the tongue this cat goes after, the nearest one if unassigned or collected.
+  target: Option<usize>,
 }
 
 impl Cat {
-  fn new(pos: Vec2, kind: CatKind) -> (Cat, Pathfinder) {
+  fn new(pos: Vec2, kind: CatKind) -> (Cat, Pathfinder, Animator, Brain) {
+    let sprite = match kind {
+      CatKind::Attacker => "cat_grey",
+      CatKind::Defender => "cat_orange",
+      CatKind::Slowing => "cat_black",
+      CatKind::Pouncer => "cat_pouncer",
+      CatKind::Sneak => "cat_sneak",
+      CatKind::Herder => "cat_herder",
+      CatKind::Splitter | CatKind::Kitten => "cat_splitter",
+    };
+    let brain = match kind {
+      CatKind::Attacker => Brain::new(pos, &[], CatState::ChaseTongue),
+      CatKind::Defender => Brain::new(
+        pos,
+        &[
+          (Condition::PlayerNear, CatState::InterceptPlayer),
+          (Condition::PlayerNearTongue, CatState::GuardTongue),
+        ],
+        CatState::ChaseTongue,
+      ),
+      CatKind::Slowing => {
+        Brain::new(pos, &[(Condition::PlayerNear, CatState::SlowPlayer)], CatState::ChaseTongue)
+      },
+      CatKind::Pouncer => {
+        Brain::new(pos, &[(Condition::PounceReady, CatState::WindUp)], CatState::ChaseTongue)
+      },
+      CatKind::Sneak => Brain::new(pos, &[], CatState::ChaseTongue),
+      CatKind::Herder => Brain::new(pos, &[], CatState::Herd),
+      CatKind::Splitter => Brain::new(pos, &[], CatState::ChaseTongue),
+      // Kittens run from a player that could send them flying.
+      CatKind::Kitten => {
+        Brain::new(pos, &[(Condition::PlayerScary, CatState::Flee)], CatState::ChaseTongue)
+      },
+    };
+
     (
       Cat {
         rect: match kind {
           CatKind::Attacker => Rect::new(pos.x, pos.y, CAT_ATTACKER_WIDTH, CAT_ATTACKER_HEIGHT),
           CatKind::Defender => Rect::new(pos.x, pos.y, CAT_DEFENDER_WIDTH, CAT_DEFENDER_HEIGHT),
           CatKind::Slowing => Rect::new(pos.x, pos.y, CAT_SLOWING_WIDTH, CAT_SLOWING_HEIGHT),
+          CatKind::Pouncer => Rect::new(pos.x, pos.y, CAT_POUNCER_WIDTH, CAT_POUNCER_HEIGHT),
+          CatKind::Sneak => Rect::new(pos.x, pos.y, CAT_SNEAK_WIDTH, CAT_SNEAK_HEIGHT),
+          CatKind::Herder => Rect::new(pos.x, pos.y, CAT_HERDER_WIDTH, CAT_HERDER_HEIGHT),
+          CatKind::Splitter => Rect::new(pos.x, pos.y, CAT_SPLITTER_WIDTH, CAT_SPLITTER_HEIGHT),
+          CatKind::Kitten => Rect::new(pos.x, pos.y, CAT_KITTEN_WIDTH, CAT_KITTEN_HEIGHT),
         },
         dir_x: 0.0,
         kind,
         speed_mul: 1.0,
         bounce_dest: Vec2::ZERO,
         bounce_percentage: None,
+        visibility: 1.0,
+        target: None,
       },
-      Pathfinder {},
+      Pathfinder::default(),
+      Animator::new(sprite),
+      brain,
+    )
+  }
+}
+
+impl Cat {
+  // The area in which a cat notices the player.
+  fn proximity(&self, tunables: &Tunables) -> Rect {
+    let proximity_range = match self.kind {
+      CatKind::Defender => tunables.cat_defender_proximity,
+      CatKind::Slowing => tunables.cat_slowing_proximity,
+      _ => 0.0,
+    };
+
+    Rect::new(
+      self.rect.x + self.rect.w / 2.0 - proximity_range,
+      self.rect.y + self.rect.h / 2.0 - proximity_range,
+      proximity_range * 2.0,
+      proximity_range * 2.0,
     )
   }
+
+  fn targeting(pos: Vec2, kind: CatKind, tongue: usize) -> (Cat, Pathfinder, Animator, Brain) {
+    let mut bundle = Cat::new(pos, kind);
+    bundle.0.target = Some(tongue);
+    bundle
+  }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 enum CatKind {
   Attacker,
   Defender,
   Slowing,
+  Pouncer,
+  Sneak,
+  Herder,
+  Splitter,
+  // What a splitter breaks into, doesn't split any further.
+  Kitten,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct Obstacle {
   rect: Rect,
   kind: ObstacleKind,
 }
 
 impl Obstacle {
-  fn new(pos: Vec2, kind: ObstacleKind) -> (Obstacle,) {
-    (Obstacle {
-      rect: match kind {
-        ObstacleKind::Maneki => {
-          Rect::new(pos.x, pos.y, OBSTACLE_MANEKI_WIDTH, OBSTACLE_MANEKI_HEIGHT)
+  fn new(pos: Vec2, kind: ObstacleKind) -> (Obstacle, Animator) {
+    let sprite = match kind {
+      ObstacleKind::Maneki => "manekineko",
+    };
+
+    (
+      Obstacle {
+        rect: match kind {
+          ObstacleKind::Maneki => {
+            Rect::new(pos.x, pos.y, OBSTACLE_MANEKI_WIDTH, OBSTACLE_MANEKI_HEIGHT)
+          },
         },
+        kind,
       },
-      kind,
-    },)
+      Animator::new(sprite),
+    )
   }
 }
 
-#[derive(PartialEq)]
+impl Obstacle {
+  // The area in which cats get sped up.
+  fn proximity(&self, tunables: &Tunables) -> Rect {
+    let proximity_range = match self.kind {
+      ObstacleKind::Maneki => tunables.obstacle_maneki_proximity,
+    };
+
+    Rect::new(
+      self.rect.x + self.rect.w / 2.0 - proximity_range,
+      self.rect.y + self.rect.h / 2.0 - proximity_range,
+      proximity_range * 2.0,
+      proximity_range * 2.0,
+    )
+  }
+}
+
+#[derive(Clone, PartialEq)]
 enum ObstacleKind {
   Maneki,
 }
 
-fn darken_background() {
-  draw_rectangle(0.0, 0.0, screen_width(), screen_height(), color_u8!(0, 0, 0, 100));
+fn darken_background(tm: Res<TextureManager>) {
+  draw_rectangle(0.0, 0.0, screen_width(), screen_height(), tm.theme.darken);
 }
 
 fn flash(flash_timer: &mut ResMut<Flash>) { flash_timer.0 = FLASH_DURATION; }
 
-fn update_flash(mut flash_timer: ResMut<Flash>) {
+fn update_flash(preferences: Res<Preferences>, mut flash_timer: ResMut<Flash>) {
   flash_timer.0 -= get_frame_time();
+  let strength = preferences.flash.strength() * flash_timer.0.max(0.0) / FLASH_DURATION;
   draw_rectangle(
     0.0,
     0.0,
     screen_width(),
     screen_height(),
-    color_u8!(255, 255, 255, (255.0 * (flash_timer.0 / FLASH_DURATION)) as u8),
+    color_u8!(255, 255, 255, (255.0 * strength) as u8),
   );
 }
 
-fn draw_ui_button(tm: &Res<TextureManager>, rect: &Rect, txt: &str) {
-  draw_rectangle(rect.x, rect.y, rect.w, rect.h, UI_FG_COLOR);
+fn draw_ui_button(tm: &TextureManager, rect: &Rect, txt: &str) {
+  draw_rectangle(rect.x, rect.y, rect.w, rect.h, tm.theme.fg);
   draw_rectangle(
     rect.x + UI_BUTTON_OUTLINE,
     rect.y + UI_BUTTON_OUTLINE,
     rect.w - UI_BUTTON_OUTLINE * 2.0,
     rect.h - UI_BUTTON_OUTLINE * 2.0,
-    UI_BG_COLOR,
+    tm.theme.bg,
   );
 
-  let text_measure = measure_text(txt, Some(tm.yoster_island), FONT_SIZE, 1.0);
-  draw_text_ex(
+  // Larger text and longer translations are shrunk to fit inside the outline both ways.
+  let mut font_size = tm.theme.font_size;
+  let mut text_measure = tm.fonts.measure(txt, font_size);
+  let max_width = rect.w - UI_BUTTON_OUTLINE * 4.0;
+  let max_height = rect.h - UI_BUTTON_OUTLINE * 2.0;
+  let fit = (max_width / text_measure.width).min(max_height / font_size as f32);
+  if fit < 1.0 {
+    font_size = (font_size as f32 * fit) as u16;
+    text_measure = tm.fonts.measure(txt, font_size);
+  }
+  tm.fonts.draw(
     txt,
     rect.center().x - text_measure.width / 2.0,
     rect.center().y + text_measure.offset_y / 2.0,
-    TextParams {
-      font: tm.yoster_island,
-      font_size: FONT_SIZE,
-      color: UI_FG_COLOR,
-      ..Default::default()
-    },
+    font_size,
+    tm.theme.fg,
   );
 }
 
 fn main_menu(
   tm: Res<TextureManager>,
+  locale: Res<Locale>,
   mut exit: ResMut<Exit>,
   mut game_state: ResMut<State<GameState>>,
   just_pressed_back_button: Res<JustPressedBackButton>,
@@ -307,19 +585,17 @@ fn main_menu(
 ) {
   let mouse_pointer: Vec2 = mouse_position().into();
 
-  draw_texture_ex(
-    tm.cgyt,
-    (screen_width() - tm.cgyt.width() * 2.0) / 2.0,
-    (screen_height() - tm.cgyt.height() * 3.5) / 2.0,
+  let logo_size = tm.atlas.size("cgyt");
+  tm.atlas.draw(
+    "cgyt",
+    (screen_width() - logo_size.x * 2.0) / 2.0,
+    (screen_height() - logo_size.y * 3.5) / 2.0,
     WHITE,
-    DrawTextureParams {
-      dest_size: Some(vec2(tm.cgyt.width(), tm.cgyt.height()) * 2.0),
-      ..Default::default()
-    },
+    DrawTextureParams { dest_size: Some(logo_size * 2.0), ..Default::default() },
   );
 
-  let play_button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 175.0, 500.0, 50.0);
-  draw_ui_button(&tm, &play_button, "Play");
+  let play_button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 175.0, 240.0, 50.0);
+  draw_ui_button(&tm, &play_button, &locale.text("menu.play"));
 
   if play_button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
     play_sound_once(tm.ui);
@@ -327,8 +603,18 @@ fn main_menu(
     let _ = game_state.overwrite_set(GameState::LevelSelect);
   }
 
-  let exit_button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 100.0, 500.0, 50.0);
-  draw_ui_button(&tm, &exit_button, "Exit");
+  let settings_button =
+    Rect::new(screen_width() / 2.0 + 10.0, screen_height() - 175.0, 240.0, 50.0);
+  draw_ui_button(&tm, &settings_button, &locale.text("menu.settings"));
+
+  if settings_button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
+    play_sound_once(tm.ui);
+    flash(&mut flash_timer);
+    let _ = game_state.overwrite_set(GameState::Settings);
+  }
+
+  let exit_button = Rect::new(screen_width() / 2.0 + 10.0, screen_height() - 100.0, 240.0, 50.0);
+  draw_ui_button(&tm, &exit_button, &locale.text("menu.exit"));
 
   #[cfg(not(target_arch = "wasm32"))]
   if exit_button.contains(mouse_pointer)
@@ -341,68 +627,62 @@ fn main_menu(
   }
 }
 
+// A button showing a powerup that cycles to the next one when clicked.
+fn powerup_select(tm: &TextureManager, button: &Rect, powerup: &mut PowerUpKind) {
+  draw_rectangle(button.x, button.y, button.w, button.h, tm.theme.fg);
+  tm.atlas.draw(
+    match *powerup {
+      PowerUpKind::SpeedUp => "speed_up_powerup",
+      PowerUpKind::NoBounce => "no_bounce_powerup",
+      PowerUpKind::Bouncier => "bouncier_powerup",
+    },
+    button.x + UI_BUTTON_OUTLINE,
+    button.y + UI_BUTTON_OUTLINE,
+    WHITE,
+    Default::default(),
+  );
+
+  if button.contains(mouse_position().into()) && is_mouse_button_pressed(MouseButton::Left) {
+    *powerup = match *powerup {
+      PowerUpKind::SpeedUp => PowerUpKind::NoBounce,
+      PowerUpKind::NoBounce => PowerUpKind::Bouncier,
+      PowerUpKind::Bouncier => PowerUpKind::SpeedUp,
+    };
+  }
+}
+
+// The level select lays its buttons out in rows of 5, `index` counts from 0.
+fn level_button(index: usize) -> Rect {
+  let startx = (screen_width() - 75.0 * 5.0) / 2.0;
+  let starty = screen_height() / 4.0 - 50.0;
+  Rect::new(startx + 75.0 * (index % 5) as f32, starty + 75.0 * (index / 5) as f32, 50.0, 50.0)
+}
+
 fn level_select(
   tm: Res<TextureManager>,
+  locale: Res<Locale>,
   mut game_state: ResMut<State<GameState>>,
   mut just_pressed_back_button: ResMut<JustPressedBackButton>,
   mut level: ResMut<Level>,
-  mut powerup: ResMut<PowerUpKind>,
   mut flash_timer: ResMut<Flash>,
 ) {
   let mouse_pointer: Vec2 = mouse_position().into();
 
-  let startx = (screen_width() - 75.0 * 5.0) / 2.0;
-  let starty = screen_height() / 4.0 - 50.0;
-  for i in 0..2 {
-    for j in 0..5 {
-      let new_level = i * 5 + j + 1;
-      if new_level > 10 {
-        break;
-      }
-
-      let x = startx + 75.0 * j as f32;
-      let y = starty + 75.0 * i as f32;
+  for index in 0..LEVEL_COUNT {
+    let new_level = index + 1;
 
-      let button = Rect::new(x, y, 50.0, 50.0);
-      draw_ui_button(&tm, &button, &new_level.to_string());
+    let button = level_button(index);
+    draw_ui_button(&tm, &button, &locale.format("level.button", &[("level", &new_level)]));
 
-      if button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
-        play_sound_once(tm.ui);
-        level.0 = new_level;
-        let _ = game_state.overwrite_set(GameState::Playing);
-      }
+    if button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
+      play_sound_once(tm.ui);
+      level.0 = new_level;
+      let _ = game_state.overwrite_set(GameState::Playing);
     }
   }
 
-  let powerup_button = Rect::new(0.0, (screen_height() - 64.0) / 2.0, 64.0, 64.0);
-  draw_rectangle(
-    powerup_button.x,
-    powerup_button.y,
-    powerup_button.w,
-    powerup_button.h,
-    UI_FG_COLOR,
-  );
-  draw_texture(
-    match *powerup {
-      PowerUpKind::SpeedUp => tm.speed_up_powerup,
-      PowerUpKind::NoBounce => tm.no_bounce_powerup,
-      PowerUpKind::Bouncier => tm.bouncier_powerup,
-    },
-    powerup_button.x + UI_BUTTON_OUTLINE,
-    powerup_button.y + UI_BUTTON_OUTLINE,
-    WHITE,
-  );
-
-  if powerup_button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
-    *powerup = match *powerup {
-      PowerUpKind::SpeedUp => PowerUpKind::NoBounce,
-      PowerUpKind::NoBounce => PowerUpKind::Bouncier,
-      PowerUpKind::Bouncier => PowerUpKind::SpeedUp,
-    };
-  }
-
   let back_button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 100.0, 500.0, 50.0);
-  draw_ui_button(&tm, &back_button, "Back");
+  draw_ui_button(&tm, &back_button, &locale.text("menu.back"));
 
   if back_button.contains(mouse_pointer) && is_mouse_button_pressed(MouseButton::Left) {
     play_sound_once(tm.ui);
@@ -413,6 +693,39 @@ fn level_select(
   }
 }
 
+// The button cycling through the play modes, and the powerups of the players in it.
+fn mode_select(
+  tm: Res<TextureManager>,
+  locale: Res<Locale>,
+  mut mode: ResMut<PlayMode>,
+  mut powerups: ResMut<PowerUps>,
+) {
+  let powerup_button = Rect::new(0.0, (screen_height() - 64.0) / 2.0, 64.0, 64.0);
+  powerup_select(&tm, &powerup_button, &mut powerups.0[PlayerId::One.index()]);
+  if *mode == PlayMode::Coop {
+    let powerup_button =
+      Rect::new(screen_width() - 64.0, (screen_height() - 64.0) / 2.0, 64.0, 64.0);
+    powerup_select(&tm, &powerup_button, &mut powerups.0[PlayerId::Two.index()]);
+  }
+
+  let mode_button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 175.0, 240.0, 50.0);
+  let text = match *mode {
+    PlayMode::Solo => locale.plural("mode.players", 1, &[]),
+    PlayMode::Coop => locale.plural("mode.players", 2, &[]),
+    PlayMode::Versus => locale.text("mode.versus"),
+  };
+  draw_ui_button(&tm, &mode_button, &text);
+
+  if mode_button.contains(mouse_position().into()) && is_mouse_button_pressed(MouseButton::Left) {
+    play_sound_once(tm.ui);
+    *mode = match *mode {
+      PlayMode::Solo => PlayMode::Coop,
+      PlayMode::Coop => PlayMode::Versus,
+      PlayMode::Versus => PlayMode::Solo,
+    };
+  }
+}
+
 fn update_misc(mut just_pressed_back_button: ResMut<JustPressedBackButton>) {
   if just_pressed_back_button.1 > 0.0 {
     just_pressed_back_button.1 -= get_frame_time();
@@ -427,47 +740,101 @@ fn despawn_all(mut commands: Commands, entities: Query<Entity>) {
   }
 }
 
-fn spawn_player(mut commands: Commands, powerup: Res<PowerUpKind>) {
-  commands.spawn_bundle(Player::new(vec2(800.0, 600.0) / 2.0, *powerup));
+fn spawn_player(
+  mut commands: Commands,
+  powerups: Res<PowerUps>,
+  mode: Res<PlayMode>,
+  generator: Res<Generator>,
+) {
+  let center = generator.layout.as_ref().map_or(vec2(800.0, 600.0) / 2.0, |layout| layout.player);
+  let powerups = generator
+    .layout
+    .as_ref()
+    .and_then(|layout| layout.powerup)
+    .map_or(powerups.0, |powerup| [powerup; 2]);
+  if *mode == PlayMode::Coop {
+    let offset = vec2(PLAYER_COOP_SPACING / 2.0, 0.0);
+    commands.spawn_bundle(Player::new(center - offset, PlayerId::One, powerups[0]));
+    commands.spawn_bundle(Player::new(center + offset, PlayerId::Two, powerups[1]));
+  } else {
+    commands.spawn_bundle(Player::new(center, PlayerId::One, powerups[0]));
+  }
 }
 
-fn spawn_tongue(mut commands: Commands, level: Res<Level>) {
-  match level.0 {
-    1 => {
-      commands.spawn_bundle(Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 50.0)));
-    },
-    2 => {
-      commands.spawn_bundle(Tongue::new(vec2(0.0, 50.0)));
-    },
-    3 => {
-      commands.spawn_bundle(Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 550.0)));
-    },
-    4 => {
-      commands.spawn_bundle(Tongue::new(vec2(150.0, (600.0 - TONGUE_WIDTH) / 2.0)));
-    },
-    5 => {
-      commands.spawn_bundle(Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 0.0)));
-    },
+fn spawn_tongue(
+  mut commands: Commands,
+  level: Res<Level>,
+  endless: Res<Endless>,
+  generator: Res<Generator>,
+  mut goal: ResMut<TongueGoal>,
+) {
+  if endless.active || generator.layout.is_some() {
+    return;
+  }
+
+  let tongues = match level.0 {
+    1 => vec![Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 50.0), Personality::Classic)],
+    2 => vec![Tongue::new(vec2(0.0, 50.0), Personality::Lazy)],
+    3 => vec![Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 550.0), Personality::Skittish)],
+    4 => vec![Tongue::new(vec2(150.0, (600.0 - TONGUE_WIDTH) / 2.0), Personality::Skittish)],
+    5 => vec![Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 0.0), Personality::Skittish)],
     6 => {
-      commands.spawn_bundle(Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 600.0 - TONGUE_HEIGHT)));
-    },
-    7 => {
-      commands.spawn_bundle(Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 600.0 - TONGUE_HEIGHT)));
-    },
-    8 => {
-      commands.spawn_bundle(Tongue::new(vec2(0.0, 600.0 - TONGUE_HEIGHT)));
-    },
-    9 => {
-      commands.spawn_bundle(Tongue::new(vec2(0.0, (600.0 - TONGUE_HEIGHT) / 2.0)));
+      vec![Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 600.0 - TONGUE_HEIGHT), Personality::Sly)]
     },
+    7 => vec![Tongue::new(
+      vec2((800.0 - TONGUE_WIDTH) / 2.0, 600.0 - TONGUE_HEIGHT),
+      Personality::Skittish,
+    )],
+    8 => vec![Tongue::new(vec2(0.0, 600.0 - TONGUE_HEIGHT), Personality::Lazy)],
+    9 => vec![Tongue::new(vec2(0.0, (600.0 - TONGUE_HEIGHT) / 2.0), Personality::Sly)],
     10 => {
-      commands.spawn_bundle(Tongue::new(vec2(800.0 - TONGUE_WIDTH, (600.0 - TONGUE_WIDTH) / 2.0)));
+      vec![Tongue::new(vec2(800.0 - TONGUE_WIDTH, (600.0 - TONGUE_WIDTH) / 2.0), Personality::Sly)]
     },
-    _ => {},
+    11 => vec![Tongue::new(vec2(0.0, 50.0), Personality::Skittish)],
+    12 => vec![Tongue::new(vec2(800.0 - TONGUE_WIDTH, 0.0), Personality::Lazy)],
+    13 => vec![Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 0.0), Personality::Skittish)],
+    14 => vec![Tongue::new(vec2(0.0, 600.0 - TONGUE_HEIGHT), Personality::Sly)],
+    15 => vec![Tongue::new(vec2(800.0 - TONGUE_WIDTH, 600.0 - TONGUE_HEIGHT), Personality::Sly)],
+    16 => vec![
+      Tongue::new(vec2(0.0, 0.0), Personality::Skittish),
+      Tongue::new(vec2(800.0 - TONGUE_WIDTH, 600.0 - TONGUE_HEIGHT), Personality::Skittish),
+    ],
+    17 => vec![
+      Tongue::new(vec2(0.0, 0.0), Personality::Lazy),
+      Tongue::new(vec2(800.0 - TONGUE_WIDTH, 0.0), Personality::Sly),
+      Tongue::new(vec2((800.0 - TONGUE_WIDTH) / 2.0, 600.0 - TONGUE_HEIGHT), Personality::Skittish),
+    ],
+    18 => vec![
+      Tongue::new(vec2(0.0, 0.0), Personality::Sly),
+      Tongue::new(vec2(800.0 - TONGUE_WIDTH, 0.0), Personality::Sly),
+      Tongue::new(vec2(0.0, 600.0 - TONGUE_HEIGHT), Personality::Skittish),
+      Tongue::new(vec2(800.0 - TONGUE_WIDTH, 600.0 - TONGUE_HEIGHT), Personality::Lazy),
+    ],
+    _ => vec![],
+  };
+  // Levels collect every tongue unless they say otherwise.
+  let required = match level.0 {
+    17 => 2,
+    _ => tongues.len(),
+  };
+  *goal = TongueGoal { collected: 0, required: Some(required), total: tongues.len() };
+
+  for (id, mut tongue) in tongues.into_iter().enumerate() {
+    tongue.0.id = id;
+    commands.spawn_bundle(tongue);
   }
 }
 
-fn spawn_cat(mut commands: Commands, level: Res<Level>) {
+fn spawn_cat(
+  mut commands: Commands,
+  level: Res<Level>,
+  endless: Res<Endless>,
+  generator: Res<Generator>,
+) {
+  if endless.active || generator.layout.is_some() {
+    return;
+  }
+
   match level.0 {
     1 => {},
     2 => {
@@ -533,11 +900,97 @@ fn spawn_cat(mut commands: Commands, level: Res<Level>) {
         CatKind::Attacker,
       ));
     },
+    11 => {
+      commands.spawn_bundle(Cat::new(
+        vec2(800.0 - CAT_POUNCER_WIDTH, 600.0 - CAT_POUNCER_HEIGHT),
+        CatKind::Pouncer,
+      ));
+    },
+    12 => {
+      commands.spawn_bundle(Cat::new(vec2(0.0, 600.0 - CAT_SNEAK_HEIGHT), CatKind::Sneak));
+      commands.spawn_bundle(Cat::new(
+        vec2(800.0 - CAT_SNEAK_WIDTH, 600.0 - CAT_SNEAK_HEIGHT),
+        CatKind::Sneak,
+      ));
+    },
+    13 => {
+      commands.spawn_bundle(Cat::new(vec2(100.0, 100.0), CatKind::Herder));
+      commands.spawn_bundle(Cat::new(
+        vec2(800.0 - CAT_ATTACKER_WIDTH, 600.0 - CAT_ATTACKER_HEIGHT),
+        CatKind::Attacker,
+      ));
+    },
+    14 => {
+      commands.spawn_bundle(Cat::new(vec2(800.0 - CAT_SPLITTER_WIDTH, 0.0), CatKind::Splitter));
+      commands.spawn_bundle(Cat::new(
+        vec2((800.0 - CAT_SPLITTER_WIDTH) / 2.0, 600.0 - CAT_SPLITTER_HEIGHT),
+        CatKind::Splitter,
+      ));
+    },
+    15 => {
+      commands.spawn_bundle(Cat::new(vec2(0.0, 0.0), CatKind::Pouncer));
+      commands.spawn_bundle(Cat::new(vec2(800.0 - CAT_SNEAK_WIDTH, 0.0), CatKind::Sneak));
+      commands.spawn_bundle(Cat::new(vec2(0.0, 600.0 - CAT_HERDER_HEIGHT), CatKind::Herder));
+      commands
+        .spawn_bundle(Cat::new(vec2((800.0 - CAT_SPLITTER_WIDTH) / 2.0, 0.0), CatKind::Splitter));
+    },
+    16 => {
+      commands.spawn_bundle(Cat::targeting(
+        vec2(800.0 - CAT_ATTACKER_WIDTH, 0.0),
+        CatKind::Attacker,
+        0,
+      ));
+      commands.spawn_bundle(Cat::targeting(
+        vec2(0.0, 600.0 - CAT_ATTACKER_HEIGHT),
+        CatKind::Attacker,
+        1,
+      ));
+    },
+    17 => {
+      commands
+        .spawn_bundle(Cat::new(vec2(0.0, (600.0 - CAT_HERDER_HEIGHT) / 2.0), CatKind::Herder));
+      commands.spawn_bundle(Cat::targeting(
+        vec2(800.0 - CAT_POUNCER_WIDTH, 600.0 - CAT_POUNCER_HEIGHT),
+        CatKind::Pouncer,
+        2,
+      ));
+      commands.spawn_bundle(Cat::new(
+        vec2(800.0 - CAT_DEFENDER_WIDTH, (600.0 - CAT_DEFENDER_HEIGHT) / 2.0),
+        CatKind::Defender,
+      ));
+    },
+    18 => {
+      commands
+        .spawn_bundle(Cat::new(vec2((800.0 - CAT_ATTACKER_WIDTH) / 2.0, 0.0), CatKind::Attacker));
+      commands.spawn_bundle(Cat::new(
+        vec2((800.0 - CAT_SNEAK_WIDTH) / 2.0, 600.0 - CAT_SNEAK_HEIGHT),
+        CatKind::Sneak,
+      ));
+      commands.spawn_bundle(Cat::targeting(
+        vec2(0.0, (600.0 - CAT_SPLITTER_HEIGHT) / 2.0),
+        CatKind::Splitter,
+        2,
+      ));
+      commands.spawn_bundle(Cat::targeting(
+        vec2(800.0 - CAT_SLOWING_WIDTH, (600.0 - CAT_SLOWING_HEIGHT) / 2.0),
+        CatKind::Slowing,
+        1,
+      ));
+    },
     _ => {},
   }
 }
 
-fn spawn_obstacle(mut commands: Commands, level: Res<Level>) {
+fn spawn_obstacle(
+  mut commands: Commands,
+  level: Res<Level>,
+  endless: Res<Endless>,
+  generator: Res<Generator>,
+) {
+  if endless.active || generator.layout.is_some() {
+    return;
+  }
+
   match level.0 {
     1 => {},
     2 => {},
@@ -569,31 +1022,53 @@ fn spawn_obstacle(mut commands: Commands, level: Res<Level>) {
       commands.spawn_bundle(Obstacle::new(vec2(900.0, 100.0), ObstacleKind::Maneki));
       commands.spawn_bundle(Obstacle::new(vec2(1200.0, 550.0), ObstacleKind::Maneki));
     },
+    11 => {},
+    12 => {},
+    13 => {
+      commands.spawn_bundle(Obstacle::new(vec2(-300.0, 100.0), ObstacleKind::Maneki));
+    },
+    14 => {},
+    15 => {
+      commands.spawn_bundle(Obstacle::new(vec2(600.0, 200.0), ObstacleKind::Maneki));
+    },
     _ => {},
   }
 }
 
-fn control_player(mut players: Query<&mut Player>, obstacles: Query<&Obstacle>) {
-  let x = (is_key_down(KeyCode::D) || is_key_down(KeyCode::Right)) as i32
-    - (is_key_down(KeyCode::A) || is_key_down(KeyCode::Left)) as i32;
-  let y = (is_key_down(KeyCode::S) || is_key_down(KeyCode::Down)) as i32
-    - (is_key_down(KeyCode::W) || is_key_down(KeyCode::Up)) as i32;
-  let trigger_powerup = is_key_pressed(KeyCode::P) || is_key_down(KeyCode::Q);
+fn control_player(
+  dt: Res<DeltaTime>,
+  tunables: Res<Tunables>,
+  inputs: Res<Inputs>,
+  mut players: Query<(&mut Player, &mut Animator)>,
+  obstacles: Query<&Obstacle>,
+  mut particles: ResMut<Particles>,
+) {
+  for (mut player, mut animator) in &mut players {
+    let input = inputs.get(player.id);
+    let (x, y, trigger_powerup) = (input.x, input.y, input.powerup);
+
+    animator.play(if player.bounce_percentage.is_some() {
+      Clip::Bounced
+    } else if x != 0 || y != 0 {
+      Clip::Walk
+    } else {
+      Clip::Idle
+    });
+    animator.speed = if player.stun_timer <= 0.0 { player.speed_mul } else { 0.0 };
 
-  for mut player in &mut players {
     if player.stun_timer <= 0.0 {
       if x != 0 {
         player.dir_x = x as f32;
       }
 
       let speed = if player.powerup_kind == PowerUpKind::SpeedUp && player.powerup_timer > 0.0 {
-        PLAYER_SPEED * PLAYER_SPEED_UP_MUL
+        tunables.player_speed * tunables.player_speed_up_mul
       } else {
-        PLAYER_SPEED
+        tunables.player_speed
       } * player.speed_mul;
 
-      player.rect.x += speed * x as f32 * get_frame_time();
-      player.rect.y += speed * y as f32 * get_frame_time();
+      player.rect.x += speed * x as f32 * dt.0;
+      player.rect.y += speed * y as f32 * dt.0;
 
       if x != 0 && y != 0 && player.bounce_percentage.is_none() {
         for obstacle in &obstacles {
@@ -602,54 +1077,43 @@ fn control_player(mut players: Query<&mut Player>, obstacles: Query<&Obstacle>)
           while let Some(intersection) = player.rect.intersect(obstacle.rect) {
             intersectioned = (intersection.w, intersection.h);
             if intersection.w > 0.0 {
-              player.rect.x -= FIX_COLLISION * x as f32 * get_frame_time();
+              player.rect.x -= FIX_COLLISION * x as f32 * dt.0;
             }
             if intersection.h > 0.0 {
-              player.rect.y -= FIX_COLLISION * y as f32 * get_frame_time();
+              player.rect.y -= FIX_COLLISION * y as f32 * dt.0;
             }
           }
 
           if (intersectioned.0 - intersectioned.1).abs() < f32::EPSILON {
           } else if intersectioned.0 > intersectioned.1 {
-            player.rect.y -= speed * y as f32 * get_frame_time();
+            player.rect.y -= speed * y as f32 * dt.0;
           } else {
-            player.rect.x -= speed * x as f32 * get_frame_time();
+            player.rect.x -= speed * x as f32 * dt.0;
           }
         }
 
         if trigger_powerup && player.powerup_cooldown_timer <= 0.0 {
           player.powerup_timer = match player.powerup_kind {
-            PowerUpKind::SpeedUp => PLAYER_SPEED_UP_TIME,
-            PowerUpKind::NoBounce => PLAYER_NO_BOUNCE_TIME,
-            PowerUpKind::Bouncier => PLAYER_BOUNCIER_TIME,
+            PowerUpKind::SpeedUp => tunables.player_speed_up_time,
+            PowerUpKind::NoBounce => tunables.player_no_bounce_time,
+            PowerUpKind::Bouncier => tunables.player_bouncier_time,
           };
-          player.powerup_cooldown_timer = PLAYER_POWERUP_COOLDOWN;
+          player.powerup_cooldown_timer = tunables.player_powerup_cooldown;
+          particles.burst(&POWERUP_ACTIVATE, player.rect.center(), Vec2::ZERO);
         } else if player.powerup_timer <= 0.0 {
-          player.powerup_cooldown_timer -= get_frame_time();
+          player.powerup_cooldown_timer -= dt.0;
         }
       }
     } else {
-      player.stun_timer -= get_frame_time();
+      player.stun_timer -= dt.0;
     }
     if player.powerup_timer > 0.0 {
-      player.powerup_timer -= get_frame_time();
+      player.powerup_timer -= dt.0;
     }
   }
 }
 
-fn animate_player(mut players: Query<&mut Player>) {
-  for mut player in &mut players {
-    if player.stun_timer <= 0.0 {
-      player.animation_timer -= get_frame_time();
-      if player.animation_timer <= 0.0 {
-        player.animation_timer = PLAYER_ANIMATION_FPS / player.speed_mul;
-        player.current_frame = 1 - player.current_frame;
-      }
-    }
-  }
-}
-
-fn bounce_player(mut players: Query<&mut Player>) {
+fn bounce_player(dt: Res<DeltaTime>, tunables: Res<Tunables>, mut players: Query<&mut Player>) {
   for mut player in &mut players {
     if let Some(percentage) = player.bounce_percentage {
       let dest = player.rect.point().lerp(player.bounce_dest, percentage.min(1.0));
@@ -659,125 +1123,416 @@ fn bounce_player(mut players: Query<&mut Player>) {
       if percentage > 1.0 {
         player.bounce_percentage = None;
       } else {
-        player.bounce_percentage = Some(percentage + 0.05);
+        player.bounce_percentage = Some(percentage + tunables.bounce_speed * dt.0);
       }
     }
   }
 }
 
 fn move_tongue(
-  mut tongues: Query<(&mut Tongue, &mut Pathfinder)>,
+  dt: Res<DeltaTime>,
+  tunables: Res<Tunables>,
+  arena: Res<Arena>,
+  mut tongues: Query<(&mut Tongue, &mut Pathfinder, &mut Animator, &mut TongueBrain)>,
   cats: Query<&Cat>,
   players: Query<&Player>,
   obstacles: Query<&Obstacle>,
 ) {
-  for (mut tongue, mut pathfinder) in &mut tongues {
-    let mut dir = Vec2::ZERO;
-    for cat in &cats {
-      dir += (cat.rect.point() - tongue.rect.point()).normalize_or_zero();
-    }
-    if dir == Vec2::ZERO {
-      for player in &players {
-        dir += (player.rect.point() - tongue.rect.point()).normalize_or_zero();
-      }
-    }
-    dir = Vec2::ZERO - dir.normalize_or_zero();
-    tongue.dir_x = dir.x;
-    let dest = tongue.rect.point() + dir * TONGUE_MAX_DEST;
+  let threats: Vec<Threat> = cats
+    .iter()
+    .map(|cat| Threat {
+      pos: cat.rect.center(),
+      weight: match cat.kind {
+        CatKind::Attacker => tunables.cat_attacker_threat,
+        CatKind::Defender => tunables.cat_defender_threat,
+        CatKind::Slowing => tunables.cat_slowing_threat,
+        CatKind::Pouncer => tunables.cat_pouncer_threat,
+        CatKind::Sneak => tunables.cat_sneak_threat,
+        CatKind::Herder => tunables.cat_herder_threat,
+        CatKind::Splitter => tunables.cat_splitter_threat,
+        CatKind::Kitten => tunables.cat_kitten_threat,
+      },
+      is_cat: true,
+    })
+    .chain(players.iter().map(|player| Threat {
+      pos: player.rect.center(),
+      weight: tunables.tongue_player_threat,
+      is_cat: false,
+    }))
+    .collect();
+  let obstacle_rects: Vec<Rect> = obstacles.iter().map(|obstacle| obstacle.rect).collect();
+
+  for (mut tongue, mut pathfinder, mut animator, mut brain) in &mut tongues {
+    let Some((dir, speed_mul)) =
+      brain.flee(tongue.rect.center(), &threats, arena.0, &obstacle_rects, &tunables, dt.0)
+    else {
+      animator.play(Clip::Idle);
+      pathfinder.dest = None;
+      continue;
+    };
+    animator.play(Clip::Walk);
 
-    pathfinder.update_pos(&mut tongue.rect, TONGUE_SPEED, dest, &obstacles, false);
+    tongue.dir_x = dir.x;
+    let dest = tongue.rect.point() + dir * tunables.tongue_max_dest;
+
+    pathfinder.update_pos(
+      &mut tongue.rect,
+      tunables.tongue_speed * speed_mul,
+      dest,
+      &obstacles,
+      false,
+      dt.0,
+    );
   }
 }
 
 fn tongue_collision(
+  mut commands: Commands,
   tm: Res<TextureManager>,
+  tongues: Query<(Entity, &Tongue)>,
+  players: Query<&Player>,
+  mut round_over: ResMut<RoundOver>,
+  mut particles: ResMut<Particles>,
+  mut goal: ResMut<TongueGoal>,
+) {
+  // A lost round can't be won anymore while the grab plays out.
+  if round_over.0.is_some() {
+    return;
+  }
+
+  for (entity, tongue) in &tongues {
+    if players.iter().any(|player| player.rect.overlaps(&tongue.rect)) {
+      commands.entity(entity).despawn();
+      particles.burst(&TONGUE_CAPTURE, tongue.rect.center(), vec2(0.0, -1.0));
+      goal.collected += 1;
+      if goal.required.is_some_and(|required| goal.collected >= required) {
+        tm.play(tm.win);
+        round_over.0 = Some(Outcome::Won);
+      } else {
+        tm.play(tm.ui);
+      }
+    }
+  }
+}
+
+fn grab_tongue(
+  tm: Res<TextureManager>,
+  god: Res<God>,
   tongues: Query<&Tongue>,
   players: Query<&Player>,
-  cats: Query<&Cat>,
-  mut game_state: ResMut<State<GameState>>,
+  mut cats: Query<(&Cat, &mut Animator)>,
+  mut round_over: ResMut<RoundOver>,
 ) {
+  // The round is decided, its slow motion plays out without the grab starting over.
+  if god.0 || round_over.0.is_some() {
+    return;
+  }
+
   for tongue in &tongues {
+    // Collected this frame, it only gets despawned at the end of the stage.
     if players.iter().any(|player| player.rect.overlaps(&tongue.rect)) {
-      play_sound_once(tm.win);
-      let _ = game_state.overwrite_set(GameState::LevelSelect);
+      continue;
     }
-    if cats.iter().any(|cat| cat.rect.overlaps(&tongue.rect)) {
-      play_sound_once(tm.lose);
-      let _ = game_state.overwrite_set(GameState::LevelSelect);
+    for (cat, mut animator) in &mut cats {
+      if cat.rect.overlaps(&tongue.rect) {
+        animator.play(Clip::Grab);
+        tm.play(tm.lose);
+        round_over.0 = Some(Outcome::Lost);
+      }
     }
   }
 }
 
+// Back to the level select, or on to the next round of a versus match.
+fn end_round(
+  mut round_over: ResMut<RoundOver>,
+  mode: Res<PlayMode>,
+  online: Option<Res<NetStatus>>,
+  mut score: ResMut<Score>,
+  mut game_state: ResMut<State<GameState>>,
+  mut time_scale: ResMut<TimeScale>,
+) {
+  let Some(outcome) = round_over.0 else {
+    return;
+  };
+  // Offline the winning catch or the grab plays out in slow motion first.
+  if online.is_none() && time_scale.slow_motion() {
+    return;
+  }
+  round_over.0 = None;
+
+  if *mode == PlayMode::Versus {
+    score.record(outcome);
+  }
+  // Online there's no level select to go back to, the level starts over instead.
+  if online.is_some() || (*mode == PlayMode::Versus && !score.decided()) {
+    let _ = game_state.restart();
+    return;
+  }
+  let _ = game_state.overwrite_set(GameState::LevelSelect);
+}
+
+const CAT_KIND_NAMES: [(&str, CatKind); 8] = [
+  ("attacker", CatKind::Attacker),
+  ("defender", CatKind::Defender),
+  ("slowing", CatKind::Slowing),
+  ("pouncer", CatKind::Pouncer),
+  ("sneak", CatKind::Sneak),
+  ("herder", CatKind::Herder),
+  ("splitter", CatKind::Splitter),
+  ("kitten", CatKind::Kitten),
+];
+
+const POWERUP_NAMES: [(&str, PowerUpKind); 3] = [
+  ("speedup", PowerUpKind::SpeedUp),
+  ("nobounce", PowerUpKind::NoBounce),
+  ("bouncier", PowerUpKind::Bouncier),
+];
+
+const COMMANDS: &[Command] = &[
+  Command { name: "level", usage: "level <number>", complete: no_completion, run: level_command },
+  Command {
+    name: "spawn",
+    usage: "spawn cat <kind> <x> <y>",
+    complete: complete_spawn,
+    run: spawn_command,
+  },
+  Command {
+    name: "powerup",
+    usage: "powerup <kind>",
+    complete: complete_powerup,
+    run: powerup_command,
+  },
+  Command { name: "god", usage: "god", complete: no_completion, run: god_command },
+  Command { name: "kill", usage: "kill cats", complete: complete_kill, run: kill_command },
+  Command { name: "win", usage: "win", complete: no_completion, run: win_command },
+  Command { name: "lose", usage: "lose", complete: no_completion, run: lose_command },
+];
+
+fn lookup<T: Copy>(names: &[(&str, T)], name: &str) -> Result<T, String> {
+  names
+    .iter()
+    .find(|(other, _)| *other == name)
+    .map(|(_, value)| *value)
+    .ok_or_else(|| format!("There's no `{}`", name))
+}
+
+fn level_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+  let level: usize = arg(args, 0)?;
+  if !(1..=LEVEL_COUNT).contains(&level) {
+    return Err(format!("Levels go from 1 to {}", LEVEL_COUNT));
+  }
+
+  // Whatever was being played before, it's a plain level now.
+  world.resource_mut::<Level>().0 = level;
+  world.resource_mut::<Endless>().active = false;
+  world.resource_mut::<Generator>().layout = None;
+  *world.resource_mut::<Daily>() = Daily::default();
+  let mut game_state = world.resource_mut::<State<GameState>>();
+  let _ = if *game_state.current() == GameState::Playing {
+    game_state.restart()
+  } else {
+    game_state.overwrite_set(GameState::Playing)
+  };
+  Ok(format!("Playing level {}", level))
+}
+
+fn complete_spawn(_: &World, index: usize) -> Vec<String> {
+  let kinds: Vec<&str> = CAT_KIND_NAMES.iter().map(|(name, _)| *name).collect();
+  complete_from(&[&["cat"], &kinds], index)
+}
+
+fn spawn_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+  require_playing(world)?;
+  let thing: String = arg(args, 0)?;
+  if thing != "cat" {
+    return Err(format!("Can't spawn `{}`", thing));
+  }
+  let kind = lookup(&CAT_KIND_NAMES, &arg::<String>(args, 1)?)?;
+  let pos = vec2(arg(args, 2)?, arg(args, 3)?);
+
+  world.spawn().insert_bundle(Cat::new(pos, kind));
+  Ok(format!("Spawned a cat at {}, {}", pos.x, pos.y))
+}
+
+fn complete_powerup(_: &World, index: usize) -> Vec<String> {
+  let kinds: Vec<&str> = POWERUP_NAMES.iter().map(|(name, _)| *name).collect();
+  complete_from(&[&kinds], index)
+}
+
+// Swaps the powerup of every player, ready to be used right away.
+fn powerup_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+  let kind = lookup(&POWERUP_NAMES, &arg::<String>(args, 0)?)?;
+
+  world.resource_mut::<PowerUps>().0 = [kind; 2];
+  for mut player in world.query::<&mut Player>().iter_mut(world) {
+    player.powerup_kind = kind;
+    player.powerup_cooldown_timer = 0.0;
+  }
+  Ok(format!("Powerup is {} now", args[0]))
+}
+
+fn god_command(world: &mut World, _: &[&str]) -> Result<String, String> {
+  let mut god = world.resource_mut::<God>();
+  god.0 = !god.0;
+  Ok(format!("God mode {}", if god.0 { "on" } else { "off" }))
+}
+
+fn complete_kill(_: &World, index: usize) -> Vec<String> { complete_from(&[&["cats"]], index) }
+
+fn kill_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+  require_playing(world)?;
+  let things: String = arg(args, 0)?;
+  if things != "cats" {
+    return Err(format!("Can't kill `{}`", things));
+  }
+
+  let cats: Vec<Entity> = world.query_filtered::<Entity, With<Cat>>().iter(world).collect();
+  for cat in &cats {
+    world.despawn(*cat);
+  }
+  Ok(format!("Killed {} cats", cats.len()))
+}
+
+// Ends the round the same way collecting the tongues or losing one does.
+fn win_command(world: &mut World, _: &[&str]) -> Result<String, String> {
+  require_playing(world)?;
+  world.resource_mut::<RoundOver>().0 = Some(Outcome::Won);
+  Ok("Won the round".to_string())
+}
+
+fn lose_command(world: &mut World, _: &[&str]) -> Result<String, String> {
+  require_playing(world)?;
+  world.resource_mut::<RoundOver>().0 = Some(Outcome::Lost);
+  Ok("Lost the round".to_string())
+}
+
 fn move_cat(
-  mut cats: Query<(&mut Cat, &mut Pathfinder)>,
+  dt: Res<DeltaTime>,
+  tunables: Res<Tunables>,
+  mut cats: Query<(Entity, &mut Cat, &mut Pathfinder, &mut Animator, &mut Brain)>,
   tongues: Query<&Tongue>,
   mut players: Query<&mut Player>,
   obstacles: Query<&Obstacle>,
+  round_over: Res<RoundOver>,
 ) {
-  for mut player in &mut players {
-    let mut player_slowed = false;
-
-    for (mut cat, mut pathfinder) in &mut cats {
-      let proximity_range = match cat.kind {
-        CatKind::Attacker => 0.0,
-        CatKind::Defender => CAT_DEFENDER_PROXIMITY,
-        CatKind::Slowing => CAT_SLOWING_PROXIMITY,
-      };
-
-      let proximity = Rect::new(
-        cat.rect.x + cat.rect.w / 2.0 - proximity_range,
-        cat.rect.y + cat.rect.h / 2.0 - proximity_range,
-        proximity_range * 2.0,
-        proximity_range * 2.0,
-      );
+  // The cat that grabbed a tongue holds on to it while the round ends, the others wait with it.
+  if round_over.0 == Some(Outcome::Lost) {
+    return;
+  }
+
+  let cat_positions: Vec<(Entity, Vec2)> =
+    cats.iter().map(|(entity, cat, ..)| (entity, cat.rect.center())).collect();
+
+  for (entity, mut cat, mut pathfinder, mut animator, mut brain) in &mut cats {
+    let pos = cat.rect.center();
+    // Cats react to whichever player is closest.
+    let Some(player) = players
+      .iter()
+      .min_by(|a, b| a.rect.center().distance(pos).total_cmp(&b.rect.center().distance(pos)))
+    else {
+      continue;
+    };
 
-      let is_player_near = proximity.overlaps(&player.rect);
+    let is_player_near = cat.proximity(&tunables).overlaps(&player.rect);
+
+    let senses = Senses {
+      pos,
+      tongue: tongues
+        .iter()
+        .find(|tongue| Some(tongue.id) == cat.target)
+        .or_else(|| {
+          tongues
+            .iter()
+            .min_by(|a, b| a.rect.center().distance(pos).total_cmp(&b.rect.center().distance(pos)))
+        })
+        .map(|tongue| tongue.rect.center()),
+      other_cat: cat_positions
+        .iter()
+        .filter(|(other, _)| *other != entity)
+        .map(|(_, other_pos)| *other_pos)
+        .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos))),
+      player: player.rect.center(),
+      player_near: is_player_near,
+      player_bouncier: player.powerup_kind == PowerUpKind::Bouncier && player.powerup_timer > 0.0,
+      bouncing: cat.bounce_percentage.is_some(),
+      roll: vec2(rand::gen_range(-1.0, 1.0), rand::gen_range(-1.0, 1.0)),
+    };
 
-      match cat.kind {
-        CatKind::Attacker => (),
-        CatKind::Defender => (),
-        CatKind::Slowing => {
-          if is_player_near {
-            player.speed_mul = CAT_SLOWING_MUL;
-            player_slowed = true;
-          }
-        },
-      }
+    brain.think(&senses, &tunables, dt.0);
+    animator.play(if brain.state == CatState::Stunned {
+      Clip::Bounced
+    } else if brain.alert() {
+      Clip::Alert
+    } else {
+      Clip::Walk
+    });
 
-      let target = if cat.kind == CatKind::Defender && is_player_near {
-        player.rect
-      } else {
-        tongues.single().rect
-      };
-
-      let dir = (target.point() - cat.rect.point()).normalize_or_zero();
-      cat.dir_x = dir.x;
-      let dest = cat.rect.point() + dir * CAT_MAX_DEST;
-
-      let speed_mul = cat.speed_mul;
-      let bounce_percentage = cat.bounce_percentage;
-      pathfinder.update_pos(
-        &mut cat.rect,
-        CAT_SPEED * speed_mul,
-        dest,
-        &obstacles,
-        bounce_percentage.is_some(),
-      );
-    }
+    let Some(target) = brain.steer(&senses, &tunables) else {
+      pathfinder.dest = None;
+      continue;
+    };
 
-    if !player_slowed {
-      player.speed_mul = 1.0;
-    }
+    let dir = (target - pos).normalize_or_zero();
+    cat.dir_x = dir.x;
+    let dest = cat.rect.point() + dir * tunables.cat_max_dest;
+
+    let speed_mul = cat.speed_mul * brain.speed_mul(&tunables);
+    let bounce_percentage = cat.bounce_percentage;
+    pathfinder.update_pos(
+      &mut cat.rect,
+      tunables.cat_speed * speed_mul,
+      dest,
+      &obstacles,
+      bounce_percentage.is_some(),
+      dt.0,
+    );
+  }
+
+  // Every player gets slowed by the slowing cats around them.
+  for mut player in &mut players {
+    let slowed = cats.iter().any(|(_, cat, _, _, brain)| {
+      brain.intent == CatState::SlowPlayer && cat.proximity(&tunables).overlaps(&player.rect)
+    });
+    player.speed_mul = if slowed { tunables.cat_slowing_mul } else { 1.0 };
+  }
+}
+
+fn fade_sneak(
+  dt: Res<DeltaTime>,
+  tunables: Res<Tunables>,
+  players: Query<&Player>,
+  mut cats: Query<(&mut Cat, &mut Animator)>,
+) {
+  for (mut cat, mut animator) in &mut cats {
+    let revealed = cat.kind != CatKind::Sneak
+      || players.iter().any(|player| {
+        player.rect.center().distance(cat.rect.center()) < tunables.cat_sneak_reveal_range
+      });
+    let target = if revealed { 1.0 } else { tunables.cat_sneak_alpha };
+    let step = tunables.cat_sneak_fade_speed * dt.0;
+    cat.visibility += (target - cat.visibility).clamp(-step, step);
+    animator.color.a = cat.visibility;
   }
 }
 
 fn cat_collision(
+  mut commands: Commands,
   tm: Res<TextureManager>,
+  tunables: Res<Tunables>,
   mut players: Query<&mut Player>,
-  mut cats: Query<&mut Cat>,
+  mut cats: Query<(Entity, &mut Cat)>,
+  mut particles: ResMut<Particles>,
+  mut camera_controller: ResMut<CameraController>,
 ) {
+  // Splitters broken this frame only get despawned at the end of the stage, another player touching
+  // them mustn't break them again.
+  let mut split = HashSet::new();
   for mut player in &mut players {
-    for mut cat in &mut cats {
+    for (entity, mut cat) in &mut cats {
+      if split.contains(&entity) {
+        continue;
+      }
       if player.rect.overlaps(&cat.rect) && player.bounce_percentage.is_none() {
         let sound = match rand::gen_range(0, 3) {
           0 => tm.bounce_1,
@@ -785,22 +1540,47 @@ fn cat_collision(
           2 => tm.bounce_3,
           _ => unreachable!(),
         };
-        play_sound_once(sound);
+        tm.play(sound);
+        particles.burst(
+          &BOUNCE_IMPACT,
+          player.rect.center().lerp(cat.rect.center(), 0.5),
+          Vec2::ZERO,
+        );
+        camera_controller.add_trauma(tunables.camera_bounce_trauma);
 
         let dir = (player.rect.center() - cat.rect.center()).normalize_or_zero();
         let powerup_mul =
           if player.powerup_kind == PowerUpKind::Bouncier && player.powerup_timer > 0.0 {
-            PLAYER_BOUNCIER_MUL
+            tunables.player_bouncier_mul
           } else {
             1.0
           };
         let cat_bounce_amount = match cat.kind {
-          CatKind::Attacker => CAT_ATTACKER_BOUNCE,
-          CatKind::Defender => CAT_DEFENDER_BOUNCE,
-          CatKind::Slowing => CAT_SLOWING_BOUNCE,
+          CatKind::Attacker => tunables.cat_attacker_bounce,
+          CatKind::Defender => tunables.cat_defender_bounce,
+          CatKind::Slowing => tunables.cat_slowing_bounce,
+          CatKind::Pouncer => tunables.cat_pouncer_bounce,
+          CatKind::Sneak => tunables.cat_sneak_bounce,
+          CatKind::Herder => tunables.cat_herder_bounce,
+          CatKind::Splitter => tunables.cat_splitter_bounce,
+          CatKind::Kitten => tunables.cat_kitten_bounce,
         };
-        cat.bounce_dest = cat.rect.point() - dir * cat_bounce_amount * powerup_mul;
-        cat.bounce_percentage = Some(0.0);
+        if cat.kind == CatKind::Splitter && powerup_mul > 1.0 {
+          // Break into two kittens flying apart from each other.
+          commands.entity(entity).despawn();
+          split.insert(entity);
+          for angle in [-tunables.cat_kitten_spread / 2.0, tunables.cat_kitten_spread / 2.0] {
+            let pos = cat.rect.center() - vec2(CAT_KITTEN_WIDTH, CAT_KITTEN_HEIGHT) / 2.0;
+            let mut kitten = Cat::new(pos, CatKind::Kitten);
+            kitten.0.bounce_dest =
+              pos - Vec2::from_angle(angle).rotate(dir) * tunables.cat_kitten_bounce * powerup_mul;
+            kitten.0.bounce_percentage = Some(0.0);
+            commands.spawn_bundle(kitten);
+          }
+        } else {
+          cat.bounce_dest = cat.rect.point() - dir * cat_bounce_amount * powerup_mul;
+          cat.bounce_percentage = Some(0.0);
+        }
         if !(player.powerup_kind == PowerUpKind::NoBounce && player.powerup_timer > 0.0) {
           player.bounce_dest = player.rect.point() + dir * cat_bounce_amount;
           player.bounce_percentage = Some(0.0);
@@ -810,7 +1590,7 @@ fn cat_collision(
   }
 }
 
-fn bounce_cat(mut cats: Query<&mut Cat>) {
+fn bounce_cat(dt: Res<DeltaTime>, tunables: Res<Tunables>, mut cats: Query<&mut Cat>) {
   for mut cat in &mut cats {
     if let Some(percentage) = cat.bounce_percentage {
       let dest = cat.rect.point().lerp(cat.bounce_dest, percentage.min(1.0));
@@ -820,29 +1600,26 @@ fn bounce_cat(mut cats: Query<&mut Cat>) {
       if percentage > 1.0 {
         cat.bounce_percentage = None;
       } else {
-        cat.bounce_percentage = Some(percentage + 0.05);
+        cat.bounce_percentage = Some(percentage + tunables.bounce_speed * dt.0);
       }
     }
   }
 }
 
-fn obstacle_maneki_update(obstacles: Query<&Obstacle>, mut cats: Query<&mut Cat>) {
+fn obstacle_maneki_update(
+  tunables: Res<Tunables>,
+  obstacles: Query<&Obstacle>,
+  mut cats: Query<&mut Cat>,
+) {
   for mut cat in &mut cats {
     let mut cat_slowed = false;
 
     for obstacle in &obstacles {
       match obstacle.kind {
         ObstacleKind::Maneki => {
-          let proximity = Rect::new(
-            obstacle.rect.x + obstacle.rect.w / 2.0 - OBSTACLE_MANEKI_PROXIMITY,
-            obstacle.rect.y + obstacle.rect.h / 2.0 - OBSTACLE_MANEKI_PROXIMITY,
-            OBSTACLE_MANEKI_PROXIMITY * 2.0,
-            OBSTACLE_MANEKI_PROXIMITY * 2.0,
-          );
-
-          let is_cat_near = proximity.overlaps(&cat.rect);
+          let is_cat_near = obstacle.proximity(&tunables).overlaps(&cat.rect);
           if is_cat_near {
-            cat.speed_mul = 1.5;
+            cat.speed_mul = tunables.obstacle_maneki_speed_mul;
             cat_slowed = true;
           }
         },
@@ -855,176 +1632,255 @@ fn obstacle_maneki_update(obstacles: Query<&Obstacle>, mut cats: Query<&mut Cat>
   }
 }
 
-fn update_camera(mut camera: ResMut<Camera2D>, players: Query<&Player>) {
-  *camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, screen_width(), screen_height()));
-  camera.target = players.single().rect.center();
-}
-
-fn draw_background(camera: Res<Camera2D>, tm: Res<TextureManager>, players: Query<&Player>) {
-  for player in &players {
-    for i in -1..2 {
-      for j in -1..2 {
-        for y in 0..screen_height() as usize / 128 + 1 {
-          for x in 0..screen_width() as usize / 128 + 1 {
-            let pos = camera.world_to_screen(
-              vec2(x as f32, y as f32) * 128.0
-                + vec2(screen_width(), screen_height())
-                  * (player.rect.center() / vec2(screen_width(), screen_height())).floor(),
-            );
-            draw_texture(
-              tm.cobblestone,
-              pos.x + j as f32 * screen_width(),
-              pos.y + i as f32 * screen_height(),
-              WHITE,
-            );
-          }
-        }
+fn draw_background(views: Res<Views>, tm: Res<TextureManager>) {
+  let tile = tm.atlas.size("cobblestone");
+
+  in_views(&views, |view| {
+    let top_left = (view.screen_to_world(view.viewport.point()) / tile).floor();
+    let bottom_right =
+      (view.screen_to_world(view.viewport.point() + view.viewport.size()) / tile).ceil();
+
+    for y in top_left.y as i32..bottom_right.y as i32 {
+      for x in top_left.x as i32..bottom_right.x as i32 {
+        let rect = view.world_to_screen_rect(Rect::new(
+          x as f32 * tile.x,
+          y as f32 * tile.y,
+          tile.x,
+          tile.y,
+        ));
+        tm.atlas.draw(
+          "cobblestone",
+          rect.x,
+          rect.y,
+          WHITE,
+          DrawTextureParams { dest_size: Some(rect.size()), ..Default::default() },
+        );
       }
     }
-  }
+  });
 }
 
-fn draw_player(camera: Res<Camera2D>, tm: Res<TextureManager>, players: Query<&Player>) {
-  for player in &players {
-    let player_pos = camera.world_to_screen(player.rect.point());
-    draw_texture_ex(
-      match player.current_frame {
-        0 => tm.skull_open,
-        1 => tm.skull_closed,
-        _ => unreachable!(),
-      },
-      player_pos.x,
-      player_pos.y,
-      WHITE,
-      DrawTextureParams {
-        dest_size: Some(player.rect.size()),
-        flip_x: player.dir_x > 0.0,
-        ..Default::default()
-      },
-    );
-  }
+fn draw_player(views: Res<Views>, tm: Res<TextureManager>, players: Query<(&Player, &Animator)>) {
+  in_views(&views, |view| {
+    for (player, animator) in &players {
+      let player_rect = view.world_to_screen_rect(player.rect);
+      animator.draw(
+        &tm.atlas,
+        player_rect.x,
+        player_rect.y,
+        DrawTextureParams {
+          dest_size: Some(player_rect.size()),
+          flip_x: player.dir_x > 0.0,
+          ..Default::default()
+        },
+      );
+    }
+  });
 }
 
-fn draw_tongue(camera: Res<Camera2D>, tm: Res<TextureManager>, tongues: Query<&Tongue>) {
-  for tongue in &tongues {
-    let tongue_pos = camera.world_to_screen(tongue.rect.point());
-    draw_texture_ex(
-      tm.tongue,
-      tongue_pos.x,
-      tongue_pos.y,
-      WHITE,
-      DrawTextureParams {
-        dest_size: Some(tongue.rect.size()),
-        flip_x: tongue.dir_x > 0.0,
-        ..Default::default()
-      },
-    );
-  }
+fn draw_tongue(views: Res<Views>, tm: Res<TextureManager>, tongues: Query<(&Tongue, &Animator)>) {
+  in_views(&views, |view| {
+    for (tongue, animator) in &tongues {
+      let tongue_rect = view.world_to_screen_rect(tongue.rect);
+      animator.draw(
+        &tm.atlas,
+        tongue_rect.x,
+        tongue_rect.y,
+        DrawTextureParams {
+          dest_size: Some(tongue_rect.size()),
+          flip_x: tongue.dir_x > 0.0,
+          ..Default::default()
+        },
+      );
+    }
+  });
 }
 
-fn draw_cat(camera: Res<Camera2D>, tm: Res<TextureManager>, cats: Query<&Cat>) {
-  for cat in &cats {
-    let cat_pos = camera.world_to_screen(cat.rect.point());
-    draw_texture_ex(
-      match cat.kind {
-        CatKind::Attacker => tm.cat_grey,
-        CatKind::Defender => tm.cat_orange,
-        CatKind::Slowing => tm.cat_black,
-      },
-      cat_pos.x,
-      cat_pos.y,
-      WHITE,
-      DrawTextureParams {
-        dest_size: Some(cat.rect.size()),
-        flip_x: cat.dir_x > 0.0,
-        ..Default::default()
-      },
-    );
-  }
+fn draw_cat(views: Res<Views>, tm: Res<TextureManager>, cats: Query<(&Cat, &Animator)>) {
+  in_views(&views, |view| {
+    for (cat, animator) in &cats {
+      let cat_rect = view.world_to_screen_rect(cat.rect);
+      animator.draw(
+        &tm.atlas,
+        cat_rect.x,
+        cat_rect.y,
+        DrawTextureParams {
+          dest_size: Some(cat_rect.size()),
+          flip_x: cat.dir_x > 0.0,
+          ..Default::default()
+        },
+      );
+    }
+  });
 }
 
-fn draw_obstacle(camera: Res<Camera2D>, tm: Res<TextureManager>, obstacles: Query<&Obstacle>) {
-  for obstacle in &obstacles {
-    let obstacle_pos = camera.world_to_screen(obstacle.rect.point());
-    draw_texture_ex(
-      match obstacle.kind {
-        ObstacleKind::Maneki => tm.manekineko,
-      },
-      obstacle_pos.x,
-      obstacle_pos.y,
-      WHITE,
-      DrawTextureParams { dest_size: Some(obstacle.rect.size()), ..Default::default() },
-    );
-  }
+fn draw_obstacle(
+  views: Res<Views>,
+  tm: Res<TextureManager>,
+  obstacles: Query<(&Obstacle, &Animator)>,
+) {
+  in_views(&views, |view| {
+    for (obstacle, animator) in &obstacles {
+      let obstacle_rect = view.world_to_screen_rect(obstacle.rect);
+      animator.draw(
+        &tm.atlas,
+        obstacle_rect.x,
+        obstacle_rect.y,
+        DrawTextureParams { dest_size: Some(obstacle_rect.size()), ..Default::default() },
+      );
+    }
+  });
+}
+
+fn enter_playing() -> SystemSet {
+  SystemSet::on_enter(GameState::Playing)
+    .with_system(despawn_all)
+    .with_system(spawn_player)
+    .with_system(spawn_tongue)
+    .with_system(spawn_cat)
+    .with_system(spawn_obstacle)
+    .with_system(generator::spawn_layout)
+    .with_system(versus::spawn_versus_cat)
+    .with_system(versus::start_round)
+    .with_system(endless::start_run)
+    .with_system(particles::clear_particles)
+    .with_system(camera::reset_camera)
+    .with_system(time::reset_slow_motion)
+}
+
+// Everything that changes the state of the game, run once per frame offline and in fixed ticks
+// that can be rolled back online. Drawing and effects stay in the main schedule.
+fn simulation() -> Schedule {
+  let mut simulation = Schedule::default().with_stage("tick", SystemStage::single_threaded());
+
+  simulation.add_system_set_to_stage("tick", State::<GameState>::get_driver());
+  simulation.add_system_to_stage("tick", tunables::scale_tunables.label("tunables"));
+  simulation.add_system_set_to_stage(
+    "tick",
+    SystemSet::on_enter(GameState::MainMenu).with_system(despawn_all).with_system(spawn_player),
+  );
+  simulation.add_system_set_to_stage(
+    "tick",
+    SystemSet::on_enter(GameState::LevelSelect).with_system(despawn_all).with_system(spawn_player),
+  );
+  simulation.add_system_set_to_stage("tick", enter_playing());
+  simulation.add_system_set_to_stage(
+    "tick",
+    SystemSet::on_update(GameState::Playing)
+      .after("tunables")
+      .with_system(input::read_inputs)
+      .with_system(control_player)
+      .with_system(bounce_player)
+      .with_system(move_tongue)
+      .with_system(tongue_collision)
+      .with_system(grab_tongue)
+      .with_system(end_round.label("end_round"))
+      .with_system(move_cat)
+      .with_system(versus::drive_cat)
+      .with_system(fade_sneak)
+      .with_system(cat_collision)
+      .with_system(bounce_cat)
+      .with_system(obstacle_maneki_update)
+      .with_system(endless::respawn_tongue)
+      .with_system(endless::spawn_wave)
+      .with_system(endless::spawn_maneki)
+      .with_system(endless::finish_run.before("end_round"))
+      .with_system(daily::time_daily.before("end_round")),
+  );
+
+  simulation
 }
 
 #[macroquad::main(window_conf)]
 async fn main() {
+  let mut session = Session::from_args().unwrap_or_else(|err| {
+    eprintln!("{}\n{}", err, NET_USAGE);
+    std::process::exit(2);
+  });
+
   let mut world = World::new();
-  world.insert_resource(State::new(GameState::MainMenu));
+  world.insert_resource(State::new(if session.is_some() {
+    GameState::Connecting
+  } else {
+    GameState::MainMenu
+  }));
   world.insert_resource(Exit(false));
   world.insert_resource(JustPressedBackButton(false, 0.0));
   world.insert_resource(Level(1));
   world.insert_resource(Flash(0.0));
-  world.insert_resource(PowerUpKind::SpeedUp);
-  world.insert_resource(Camera2D::from_display_rect(Rect::new(
-    0.0,
-    0.0,
-    screen_width(),
-    screen_height(),
+  world.insert_resource(PowerUps([PowerUpKind::SpeedUp; 2]));
+  world.insert_resource(PlayMode::Solo);
+  world.insert_resource(VersusCat(CatKind::Attacker));
+  world.insert_resource(Score::default());
+  world.insert_resource(RoundOver(None));
+  world.insert_resource(DeltaTime(0.0));
+  world.insert_resource(TimeScale::default());
+  world.insert_resource(God(false));
+  world.insert_resource(Cheated(false));
+  world.insert_resource(Inputs::default());
+  world.insert_resource(Particles::default());
+  world.insert_resource(hud::Minimap(true));
+  world.insert_resource(debug::DebugOverlay(false));
+  let mut console = Console::default();
+  console.register(COMMANDS);
+  console.register(tunables::COMMANDS);
+  console.register(time::COMMANDS);
+  world.insert_resource(console);
+  world.insert_resource(Inspector::default());
+  world.insert_resource(Views::new());
+  world.insert_resource(CameraController::new());
+  world.insert_resource(TongueGoal { collected: 0, required: Some(0), total: 0 });
+  world.insert_resource(Endless::default());
+  world.insert_resource(HighScores::load());
+  world.insert_resource(Generator::new());
+  world.insert_resource(Daily::default());
+  world.insert_resource(DailyHistory::load());
+  world.insert_resource(Difficulty::load());
+  world.insert_resource(BaseTunables(Tunables::load()));
+  let preferences = Preferences::load();
+  let language = preferences.language.clone().or_else(locale::system_language);
+  world.insert_resource(Locale::load(language.as_deref().unwrap_or("en")));
+  let theme = preferences.theme();
+  world.insert_resource(preferences);
+  world.insert_resource(Tunables::default());
+  #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+  world.insert_resource(tunables::TunablesWatcher::new());
+  world.insert_resource(Arena(Rect::new(
+    -ARENA_PADDING,
+    -ARENA_PADDING,
+    LEVEL_WIDTH + ARENA_PADDING * 2.0,
+    LEVEL_HEIGHT + ARENA_PADDING * 2.0,
   )));
 
-  let tm = TextureManager {
-    bounce_1: load_sound("res/bounce_1.wav").await.unwrap(),
-    bounce_2: load_sound("res/bounce_2.wav").await.unwrap(),
-    bounce_3: load_sound("res/bounce_3.wav").await.unwrap(),
-    bouncier_powerup: load_texture("res/bouncier_powerup.png").await.unwrap(),
-    cat_black: load_texture("res/cat_black.png").await.unwrap(),
-    cat_grey: load_texture("res/cat_grey.png").await.unwrap(),
-    cat_orange: load_texture("res/cat_orange.png").await.unwrap(),
-    cgyt: load_texture("res/cgyt.png").await.unwrap(),
-    cobblestone: load_texture("res/cobblestone.png").await.unwrap(),
-    lose: load_sound("res/lose.wav").await.unwrap(),
-    manekineko: load_texture("res/manekineko.png").await.unwrap(),
-    no_bounce_powerup: load_texture("res/no_bounce_powerup.png").await.unwrap(),
-    skull_closed: load_texture("res/skull_closed.png").await.unwrap(),
-    skull_open: load_texture("res/skull_open.png").await.unwrap(),
-    speed_up_powerup: load_texture("res/speed_up_powerup.png").await.unwrap(),
-    tongue: load_texture("res/tongue.png").await.unwrap(),
-    ui: load_sound("res/ui.wav").await.unwrap(),
-    win: load_sound("res/win.wav").await.unwrap(),
-    yoster_island: load_ttf_font("res/yoster-island.ttf").await.unwrap(),
-  };
-
-  tm.bouncier_powerup.set_filter(FilterMode::Nearest);
-  tm.cat_black.set_filter(FilterMode::Nearest);
-  tm.cat_grey.set_filter(FilterMode::Nearest);
-  tm.cat_orange.set_filter(FilterMode::Nearest);
-  tm.cgyt.set_filter(FilterMode::Nearest);
-  tm.cobblestone.set_filter(FilterMode::Nearest);
-  tm.manekineko.set_filter(FilterMode::Nearest);
-  tm.no_bounce_powerup.set_filter(FilterMode::Nearest);
-  tm.skull_closed.set_filter(FilterMode::Nearest);
-  tm.skull_open.set_filter(FilterMode::Nearest);
-  tm.speed_up_powerup.set_filter(FilterMode::Nearest);
-  tm.tongue.set_filter(FilterMode::Nearest);
-
-  world.insert_resource(tm);
+  world.insert_resource(TextureManager::load(theme).await);
+  #[cfg(feature = "hot-reload")]
+  world.insert_resource(hot_reload::AssetWatcher::new());
 
+  let mut simulation = simulation();
   let mut schedule = Schedule::default()
     .with_stage("update", SystemStage::single_threaded())
     .with_stage_after("update", "late_update", SystemStage::single_threaded());
 
   schedule.add_system_set_to_stage("update", State::<GameState>::get_driver());
   schedule.add_system_set_to_stage("late_update", State::<GameState>::get_driver());
+  #[cfg(feature = "hot-reload")]
+  schedule.add_system_to_stage("update", hot_reload::watch_assets);
+  #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+  schedule.add_system_to_stage("update", tunables::watch_tunables);
 
   schedule.add_system_set_to_stage(
-    "update",
-    SystemSet::on_enter(GameState::MainMenu).with_system(despawn_all).with_system(spawn_player),
+    "late_update",
+    SystemSet::on_update(GameState::Connecting)
+      .with_system(draw_background.label("background"))
+      .with_system(darken_background.label("darken_background").after("background"))
+      .with_system(net::draw_status.after("darken_background")),
   );
+
   schedule.add_system_set_to_stage(
     "update",
-    SystemSet::on_update(GameState::MainMenu).with_system(update_misc).with_system(update_camera),
+    SystemSet::on_update(GameState::MainMenu)
+      .with_system(update_misc)
+      .with_system(camera::update_camera),
   );
   schedule.add_system_set_to_stage(
     "late_update",
@@ -1032,69 +1888,116 @@ async fn main() {
       .with_system(draw_background.label("background"))
       .with_system(darken_background.label("darken_background").after("background"))
       .with_system(main_menu.after("darken_background"))
+      .with_system(daily::daily_select.after("darken_background"))
+      .with_system(daily::draw_daily_select.after("darken_background"))
+      .with_system(difficulty::difficulty_select.after("darken_background"))
       .with_system(update_flash.after("darken_background")),
   );
 
   schedule.add_system_set_to_stage(
     "update",
-    SystemSet::on_enter(GameState::LevelSelect).with_system(despawn_all).with_system(spawn_player),
+    SystemSet::on_update(GameState::Settings)
+      .with_system(update_misc)
+      .with_system(camera::update_camera),
   );
+  schedule.add_system_set_to_stage(
+    "late_update",
+    SystemSet::on_update(GameState::Settings)
+      .with_system(draw_background.label("background"))
+      .with_system(darken_background.label("darken_background").after("background"))
+      .with_system(settings::settings_menu.after("darken_background"))
+      .with_system(update_flash.after("darken_background")),
+  );
+
   schedule.add_system_set_to_stage(
     "update",
     SystemSet::on_update(GameState::LevelSelect)
       .with_system(update_misc)
-      .with_system(update_camera),
+      .with_system(camera::update_camera)
+      .with_system(particles::update_particles),
   );
   schedule.add_system_set_to_stage(
     "late_update",
     SystemSet::on_update(GameState::LevelSelect)
       .with_system(draw_background.label("background"))
-      .with_system(darken_background.label("darken_background").after("background"))
+      .with_system(particles::draw_particles.label("particles").after("background"))
+      .with_system(darken_background.label("darken_background").after("particles"))
       .with_system(level_select.after("darken_background"))
+      .with_system(mode_select.after("darken_background"))
+      .with_system(versus::versus_select.after("darken_background"))
+      .with_system(endless::endless_select.after("darken_background"))
+      .with_system(generator::generator_select.after("darken_background"))
       .with_system(update_flash.after("darken_background")),
   );
 
-  schedule.add_system_set_to_stage(
-    "late_update",
-    SystemSet::on_enter(GameState::Playing)
-      .with_system(despawn_all)
-      .with_system(spawn_player)
-      .with_system(spawn_tongue)
-      .with_system(spawn_cat)
-      .with_system(spawn_obstacle),
-  );
+  // The level select enters the level from here, rounds restarting do it from the simulation, and a
+  // transition only runs the enter systems of the stage it happens in.
+  schedule.add_system_set_to_stage("late_update", enter_playing());
   schedule.add_system_set_to_stage(
     "update",
     SystemSet::on_update(GameState::Playing)
-      .with_system(control_player)
-      .with_system(animate_player)
-      .with_system(bounce_player)
-      .with_system(move_tongue)
-      .with_system(tongue_collision)
-      .with_system(move_cat)
-      .with_system(cat_collision)
-      .with_system(bounce_cat)
-      .with_system(obstacle_maneki_update)
-      .with_system(update_camera),
+      .with_system(animation::animate)
+      .with_system(camera::update_camera)
+      .with_system(particles::emit_speed_trail)
+      .with_system(hud::toggle_minimap)
+      .with_system(debug::toggle_debug)
+      .with_system(inspector::toggle_inspector)
+      .with_system(time::control_time)
+      .with_system(particles::update_particles),
   );
   schedule.add_system_set_to_stage(
     "late_update",
     SystemSet::on_update(GameState::Playing)
       .with_system(draw_background.label("background"))
-      .with_system(draw_player.after("background"))
-      .with_system(draw_tongue.after("background"))
-      .with_system(draw_cat.after("background"))
-      .with_system(draw_obstacle.after("background")),
+      .with_system(draw_player.label("world").after("background"))
+      .with_system(draw_tongue.label("world").after("background"))
+      .with_system(draw_cat.label("world").after("background"))
+      .with_system(draw_obstacle.label("world").after("background"))
+      .with_system(particles::draw_particles.label("world").after("background"))
+      .with_system(hud::draw_offscreen_indicators.label("hud").after("world"))
+      .with_system(hud::draw_minimap_frame.label("minimap").after("world"))
+      .with_system(hud::draw_minimap.label("hud").after("minimap"))
+      .with_system(hud::draw_tongue_counter.label("hud").after("world"))
+      .with_system(hud::draw_split_line.label("hud").after("world"))
+      .with_system(hud::draw_cat_badges.label("hud").after("world"))
+      .with_system(versus::draw_score.label("hud").after("world"))
+      .with_system(endless::draw_run.label("hud").after("world"))
+      .with_system(generator::draw_seed.label("hud").after("world"))
+      .with_system(daily::draw_daily.label("hud").after("world"))
+      .with_system(net::draw_status.label("hud").after("world"))
+      .with_system(debug::draw_debug_rects.after("hud"))
+      .with_system(debug::draw_debug_targets.after("hud"))
+      .with_system(debug::draw_frame_stats.after("hud"))
+      .with_system(inspector::draw_inspector.after("hud")),
   );
 
+  // Runs in every state, on top of everything else.
+  schedule.add_system_to_stage("late_update", console::run_console.exclusive_system().at_end());
+
   play_sound(
-    load_sound("res/song.wav").await.unwrap(),
+    assets::load_sound("song.wav").await.unwrap(),
     PlaySoundParams { looped: true, volume: 0.3 },
   );
 
   loop {
     clear_background(BLACK);
 
+    #[cfg(feature = "hot-reload")]
+    hot_reload::reload_changed_assets(&mut world).await;
+
+    match &mut session {
+      Some(session) => {
+        // Online the game always runs at normal speed.
+        world.resource_mut::<TimeScale>().delta = get_frame_time();
+        session.advance(&mut world, &mut simulation);
+      },
+      None => {
+        if let Some(dt) = world.resource_mut::<TimeScale>().advance() {
+          world.insert_resource(DeltaTime(dt));
+          simulation.run(&mut world);
+        }
+      },
+    }
     schedule.run(&mut world);
     if world.resource::<Exit>().0 {
       break;
@@ -0,0 +1,695 @@
+use std::collections::{
+  HashMap,
+  VecDeque,
+};
+use std::env;
+use std::hash::{
+  Hash,
+  Hasher,
+};
+use std::net::{
+  SocketAddr,
+  UdpSocket,
+};
+use std::str::FromStr;
+use std::time::{
+  Duration,
+  Instant,
+  SystemTime,
+  UNIX_EPOCH,
+};
+
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+use crate::animation::Animator;
+use crate::behavior::{
+  Brain,
+  TongueBrain,
+};
+use crate::camera::CameraController;
+use crate::difficulty::Difficulty;
+use crate::input::{
+  self,
+  Inputs,
+  PlayerId,
+  PlayerInput,
+};
+use crate::locale::Locale;
+use crate::particles::Particles;
+use crate::versus::{
+  Driven,
+  Score,
+};
+use crate::{
+  draw_ui_button,
+  Cat,
+  DeltaTime,
+  GameState,
+  Level,
+  Obstacle,
+  Pathfinder,
+  PlayMode,
+  Player,
+  RoundOver,
+  TextureManager,
+  Tongue,
+  TongueGoal,
+  LEVEL_COUNT,
+};
+
+const NET_TICK_TIME: f32 = 1.0 / 60.0;
+// Local inputs are applied this many ticks after they are read, which hides a bit of the latency.
+const NET_INPUT_DELAY: u32 = 2;
+// The simulation waits for the other player rather than guess further ahead than this.
+const NET_MAX_PREDICTION: u32 = 10;
+// A slow frame doesn't get caught up with more ticks than this.
+const NET_MAX_TICKS_PER_FRAME: u32 = 4;
+const NET_MAX_INPUTS_PER_PACKET: usize = 64;
+const NET_MAX_PACKET: usize = 2048;
+const NET_HELLO_INTERVAL: f32 = 0.5;
+// Without any packet for this long the other player counts as gone.
+const NET_TIMEOUT: f32 = 1.0;
+const NET_CHECKSUM_INTERVAL: u32 = 30;
+// Checksums the other player never answered are forgotten after this many ticks.
+const NET_CHECKSUM_KEEP: u32 = 600;
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// Printed when the command line arguments of an online match are wrong.
+pub const NET_USAGE: &str = "Usage: --net <bind address> <peer address> [--player 1|2] \
+                             [--level <level>] [--versus] [--latency <ms>] [--jitter <ms>] \
+                             [--loss <percent>]";
+
+// What the host decides for both players.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Settings {
+  level: usize,
+  versus: bool,
+  difficulty: Difficulty,
+  seed: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Message {
+  // Sent until the other player answers, the host's carries the settings of the match.
+  Hello { settings: Option<Settings> },
+  // The sender's inputs from tick `start` on, which are sent again until they're acknowledged so
+  // lost packets don't matter. `ack` is how many of the receiver's inputs the sender has.
+  Input { ack: u32, start: u32, inputs: Vec<u8> },
+  // A hash of the state before `tick`, to find out when the two simulations drift apart.
+  Checksum { tick: u32, value: u64 },
+}
+
+// Bad network conditions to try the game under, applied to every packet sent.
+struct Conditions {
+  latency: Duration,
+  jitter: Duration,
+  // Chance of a packet getting lost, from 0 to 1.
+  loss: f32,
+}
+
+struct Link {
+  socket: UdpSocket,
+  peer: SocketAddr,
+  conditions: Conditions,
+  // Packets held back by the simulated latency, with the time they go out.
+  delayed: Vec<(Instant, Vec<u8>)>,
+  // The simulated conditions have their own random numbers, the game's ones have to stay in sync.
+  rng: u64,
+}
+
+impl Link {
+  fn new(bind: SocketAddr, peer: SocketAddr, conditions: Conditions) -> Result<Link, String> {
+    let socket = UdpSocket::bind(bind)
+      .and_then(|socket| socket.set_nonblocking(true).map(|()| socket))
+      .map_err(|err| format!("Couldn't bind to `{}`: {}", bind, err))?;
+
+    Ok(Link { socket, peer, conditions, delayed: Vec::new(), rng: seed() | 1 })
+  }
+
+  // Xorshift, from 0 to 1.
+  fn random(&mut self) -> f32 {
+    self.rng ^= self.rng << 13;
+    self.rng ^= self.rng >> 7;
+    self.rng ^= self.rng << 17;
+    (self.rng >> 40) as f32 / (1 << 24) as f32
+  }
+
+  fn send(&mut self, message: &Message) {
+    if self.random() < self.conditions.loss {
+      return;
+    }
+
+    let delay = self.conditions.latency + self.conditions.jitter.mul_f32(self.random());
+    match ron::to_string(message) {
+      Ok(packet) => self.delayed.push((Instant::now() + delay, packet.into_bytes())),
+      Err(err) => warn!("Failed to serialize a packet: {}", err),
+    }
+  }
+
+  fn flush(&mut self) {
+    let now = Instant::now();
+    let (socket, peer) = (&self.socket, self.peer);
+    self.delayed.retain(|(due, packet)| {
+      if *due > now {
+        return true;
+      }
+      let _ = socket.send_to(packet, peer);
+      false
+    });
+  }
+
+  fn receive(&mut self) -> Vec<Message> {
+    let mut buffer = [0; NET_MAX_PACKET];
+    let mut messages = Vec::new();
+    while let Ok((len, from)) = self.socket.recv_from(&mut buffer) {
+      if from != self.peer {
+        continue;
+      }
+      if let Some(message) =
+        std::str::from_utf8(&buffer[..len]).ok().and_then(|text| ron::from_str(text).ok())
+      {
+        messages.push(message);
+      }
+    }
+    messages
+  }
+}
+
+fn seed() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64)
+}
+
+// FNV-1a, unlike the standard library's hasher it's the same in every build and on every platform, so
+// both players get the same checksum for the same state.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+  fn write(&mut self, bytes: &[u8]) {
+    for byte in bytes {
+      self.0 = (self.0 ^ *byte as u64).wrapping_mul(FNV_PRIME);
+    }
+  }
+
+  // Sizes are hashed as 64 bits, whatever the pointer width of the build.
+  fn write_usize(&mut self, value: usize) { self.write(&(value as u64).to_le_bytes()); }
+
+  fn finish(&self) -> u64 { self.0 }
+}
+
+#[derive(Clone)]
+struct SavedEntity {
+  player: Option<Player>,
+  tongue: Option<Tongue>,
+  cat: Option<Cat>,
+  obstacle: Option<Obstacle>,
+  pathfinder: Option<Pathfinder>,
+  animator: Option<Animator>,
+  brain: Option<Brain>,
+  tongue_brain: Option<TongueBrain>,
+  driven: Option<Driven>,
+}
+
+// Everything the simulation changes before `tick`, to go back to when a guessed input was wrong.
+struct Snapshot {
+  tick: u32,
+  entities: Vec<SavedEntity>,
+  round_over: RoundOver,
+  goal: TongueGoal,
+  score: Score,
+}
+
+impl Snapshot {
+  fn save(world: &mut World, tick: u32) -> Snapshot {
+    let entities = world
+      .query::<(
+        Option<&Player>,
+        Option<&Tongue>,
+        Option<&Cat>,
+        Option<&Obstacle>,
+        Option<&Pathfinder>,
+        Option<&Animator>,
+        Option<&Brain>,
+        Option<&TongueBrain>,
+        Option<&Driven>,
+      )>()
+      .iter(world)
+      .map(|(player, tongue, cat, obstacle, pathfinder, animator, brain, tongue_brain, driven)| {
+        SavedEntity {
+          player: player.cloned(),
+          tongue: tongue.cloned(),
+          cat: cat.cloned(),
+          obstacle: obstacle.cloned(),
+          pathfinder: pathfinder.cloned(),
+          animator: animator.cloned(),
+          brain: brain.cloned(),
+          tongue_brain: tongue_brain.cloned(),
+          driven: driven.cloned(),
+        }
+      })
+      .collect();
+
+    Snapshot {
+      tick,
+      entities,
+      round_over: world.resource::<RoundOver>().clone(),
+      goal: world.resource::<TongueGoal>().clone(),
+      score: world.resource::<Score>().clone(),
+    }
+  }
+
+  // Respawns the entities in the order they were saved in, so queries go over them in the same order
+  // as for the other player.
+  fn restore(&self, world: &mut World) {
+    let entities: Vec<Entity> = world.query::<Entity>().iter(world).collect();
+    for entity in entities {
+      world.despawn(entity);
+    }
+
+    for saved in self.entities.iter().cloned() {
+      let mut entity = world.spawn();
+      if let Some(player) = saved.player {
+        entity.insert(player);
+      }
+      if let Some(tongue) = saved.tongue {
+        entity.insert(tongue);
+      }
+      if let Some(cat) = saved.cat {
+        entity.insert(cat);
+      }
+      if let Some(obstacle) = saved.obstacle {
+        entity.insert(obstacle);
+      }
+      if let Some(pathfinder) = saved.pathfinder {
+        entity.insert(pathfinder);
+      }
+      if let Some(animator) = saved.animator {
+        entity.insert(animator);
+      }
+      if let Some(brain) = saved.brain {
+        entity.insert(brain);
+      }
+      if let Some(tongue_brain) = saved.tongue_brain {
+        entity.insert(tongue_brain);
+      }
+      if let Some(driven) = saved.driven {
+        entity.insert(driven);
+      }
+    }
+
+    world.insert_resource(self.round_over.clone());
+    world.insert_resource(self.goal.clone());
+    world.insert_resource(self.score.clone());
+  }
+
+  fn checksum(&self) -> u64 {
+    let mut hasher = Fnv(FNV_OFFSET);
+    let mut hash_floats = |floats: &[f32]| {
+      for float in floats {
+        hasher.write(&float.to_bits().to_le_bytes());
+      }
+    };
+
+    for saved in &self.entities {
+      let rects = [
+        saved.player.as_ref().map(|player| player.rect),
+        saved.tongue.as_ref().map(|tongue| tongue.rect),
+        saved.cat.as_ref().map(|cat| cat.rect),
+        saved.obstacle.as_ref().map(|obstacle| obstacle.rect),
+      ];
+      for rect in rects.into_iter().flatten() {
+        hash_floats(&[rect.x, rect.y, rect.w, rect.h]);
+      }
+      if let Some(player) = &saved.player {
+        hash_floats(&[player.stun_timer, player.powerup_timer, player.powerup_cooldown_timer]);
+      }
+      if let Some(cat) = &saved.cat {
+        hash_floats(&[cat.bounce_percentage.unwrap_or(-1.0)]);
+      }
+    }
+    self.round_over.hash(&mut hasher);
+    self.goal.hash(&mut hasher);
+    self.score.hash(&mut hasher);
+    hasher.finish()
+  }
+}
+
+// How the online match is going, for the HUD and for systems that act differently online.
+pub struct NetStatus {
+  peer: SocketAddr,
+  connected: bool,
+  // The first tick the two players' games were found to differ at.
+  desync: Option<u32>,
+}
+
+// An online match against one other player. Both run the same fixed tick simulation, every tick
+// uses the local input and the other player's one, guessed to be the same as their last one until
+// it arrives. A wrong guess rolls the world back to that tick and simulates it again.
+pub struct Session {
+  link: Link,
+  local: PlayerId,
+  // Known from the start by the host, sent over by it otherwise.
+  settings: Option<Settings>,
+  peer_seen: bool,
+  started: bool,
+  hello_timer: f32,
+  // Time since the last packet from the other player.
+  silence: f32,
+  accumulator: f32,
+  // The next tick to simulate.
+  tick: u32,
+  local_inputs: Vec<PlayerInput>,
+  remote_inputs: Vec<PlayerInput>,
+  // The remote input every simulated tick used, guessed or not.
+  used_remote: Vec<PlayerInput>,
+  // How many of the local inputs the other player has.
+  peer_ack: u32,
+  rollback: Option<u32>,
+  snapshots: VecDeque<Snapshot>,
+  next_check: u32,
+  local_checksums: HashMap<u32, u64>,
+  remote_checksums: HashMap<u32, u64>,
+  desync: Option<u32>,
+}
+
+fn flag<T: FromStr>(args: &[String], name: &str, default: T) -> Result<T, String> {
+  match args.iter().position(|arg| arg == name) {
+    Some(i) => args
+      .get(i + 1)
+      .and_then(|value| value.parse().ok())
+      .ok_or_else(|| format!("`{}` needs a valid value", name)),
+    None => Ok(default),
+  }
+}
+
+impl Session {
+  // `--net <bind address> <peer address>` starts an online match, the host (`--player 1`, the
+  // default) picks the `--level` and whether it's `--versus`. `--latency` and `--jitter` in
+  // milliseconds and `--loss` in percent simulate a bad connection. `None` without `--net`.
+  pub fn from_args() -> Result<Option<Session>, String> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(net) = args.iter().position(|arg| arg == "--net") else {
+      return Ok(None);
+    };
+    let address = |i: usize| -> Result<SocketAddr, String> {
+      let address = args.get(i).ok_or("`--net` needs a bind address and a peer address")?;
+      address.parse().map_err(|_| format!("`{}` isn't an address", address))
+    };
+    let (bind, peer) = (address(net + 1)?, address(net + 2)?);
+
+    let local = match flag(&args, "--player", 1)? {
+      1 => PlayerId::One,
+      2 => PlayerId::Two,
+      _ => return Err("`--player` is either 1 or 2".to_string()),
+    };
+    let settings = match local {
+      PlayerId::One => Some(Settings {
+        level: flag(&args, "--level", 1)?.clamp(1, LEVEL_COUNT),
+        versus: args.iter().any(|arg| arg == "--versus"),
+        difficulty: Difficulty::load(),
+        seed: seed(),
+      }),
+      PlayerId::Two => None,
+    };
+    let loss: f32 = flag(&args, "--loss", 0.0)?;
+    if !(0.0..=100.0).contains(&loss) {
+      return Err("`--loss` is a percentage from 0 to 100".to_string());
+    }
+    let conditions = Conditions {
+      latency: Duration::from_millis(flag(&args, "--latency", 0)?),
+      jitter: Duration::from_millis(flag(&args, "--jitter", 0)?),
+      loss: loss / 100.0,
+    };
+
+    Ok(Some(Session {
+      link: Link::new(bind, peer, conditions)?,
+      local,
+      settings,
+      peer_seen: false,
+      started: false,
+      hello_timer: 0.0,
+      silence: 0.0,
+      accumulator: 0.0,
+      tick: 0,
+      local_inputs: vec![PlayerInput::default(); NET_INPUT_DELAY as usize],
+      remote_inputs: Vec::new(),
+      used_remote: Vec::new(),
+      peer_ack: 0,
+      rollback: None,
+      snapshots: VecDeque::new(),
+      next_check: 0,
+      local_checksums: HashMap::new(),
+      remote_checksums: HashMap::new(),
+      desync: None,
+    }))
+  }
+
+  fn remote(&self) -> PlayerId {
+    match self.local {
+      PlayerId::One => PlayerId::Two,
+      PlayerId::Two => PlayerId::One,
+    }
+  }
+
+  // Runs as many ticks as the time since the last frame allows, after fixing up wrong guesses.
+  pub fn advance(&mut self, world: &mut World, simulation: &mut Schedule) {
+    world.insert_resource(NetStatus {
+      peer: self.link.peer,
+      connected: self.peer_seen && self.silence < NET_TIMEOUT,
+      desync: self.desync,
+    });
+
+    let dt = get_frame_time();
+    self.silence += dt;
+    for message in self.link.receive() {
+      self.silence = 0.0;
+      self.handle(message);
+    }
+
+    if !self.started {
+      self.hello_timer -= dt;
+      if self.hello_timer <= 0.0 {
+        self.hello_timer = NET_HELLO_INTERVAL;
+        let settings = if self.local == PlayerId::One { self.settings } else { None };
+        self.link.send(&Message::Hello { settings });
+      }
+      if let Some(settings) = self.settings.filter(|_| self.peer_seen) {
+        self.start(world, simulation, settings);
+      }
+    } else {
+      self.resimulate(world, simulation);
+
+      self.accumulator =
+        (self.accumulator + dt).min(NET_TICK_TIME * NET_MAX_TICKS_PER_FRAME as f32);
+      while self.accumulator >= NET_TICK_TIME
+        && self.tick < self.remote_inputs.len() as u32 + NET_MAX_PREDICTION
+      {
+        self.accumulator -= NET_TICK_TIME;
+        while self.local_inputs.len() <= (self.tick + NET_INPUT_DELAY) as usize {
+          self.local_inputs.push(input::read_local());
+        }
+        self.simulate(world, simulation);
+      }
+
+      self.check_sync();
+      self.send_inputs();
+    }
+    self.link.flush();
+  }
+
+  fn handle(&mut self, message: Message) {
+    match message {
+      Message::Hello { settings } => {
+        self.peer_seen = true;
+        if self.local == PlayerId::Two && self.settings.is_none() {
+          self.settings = settings;
+        }
+      },
+      Message::Input { ack, start, inputs } => {
+        self.peer_seen = true;
+        self.peer_ack = self.peer_ack.max(ack);
+        for (tick, bits) in (start as usize..).zip(inputs) {
+          if tick != self.remote_inputs.len() {
+            continue;
+          }
+          let input = PlayerInput::from_bits(bits);
+          if self.used_remote.get(tick).is_some_and(|used| *used != input) {
+            self.rollback = Some(self.rollback.map_or(tick as u32, |from| from.min(tick as u32)));
+          }
+          self.remote_inputs.push(input);
+        }
+      },
+      Message::Checksum { tick, value } => {
+        self.remote_checksums.insert(tick, value);
+        self.compare(tick);
+      },
+    }
+  }
+
+  fn start(&mut self, world: &mut World, simulation: &mut Schedule, settings: Settings) {
+    self.started = true;
+    world.insert_resource(Level(settings.level));
+    world.insert_resource(if settings.versus { PlayMode::Versus } else { PlayMode::Coop });
+    world.insert_resource(settings.difficulty);
+    let _ = world.resource_mut::<State<GameState>>().overwrite_set(GameState::Playing);
+
+    // The level is entered through a tick without any time passing, so both players spawn it the
+    // same way.
+    world.insert_resource(Inputs::default());
+    world.insert_resource(DeltaTime(0.0));
+    rand::srand(settings.seed);
+    simulation.run(world);
+  }
+
+  fn simulate(&mut self, world: &mut World, simulation: &mut Schedule) {
+    let tick = self.tick as usize;
+    self.snapshots.retain(|snapshot| snapshot.tick < self.tick);
+    self.snapshots.push_back(Snapshot::save(world, self.tick));
+
+    let remote =
+      self.remote_inputs.get(tick).or(self.remote_inputs.last()).copied().unwrap_or_default();
+    self.used_remote.truncate(tick);
+    self.used_remote.push(remote);
+
+    let mut inputs = Inputs::default();
+    inputs.set(self.local, self.local_inputs[tick]);
+    inputs.set(self.remote(), remote);
+    world.insert_resource(inputs);
+    world.insert_resource(DeltaTime(NET_TICK_TIME));
+    // Both players get the same random numbers no matter how often they simulated a tick.
+    rand::srand(self.settings.map_or(0, |settings| settings.seed) ^ self.tick as u64);
+    simulation.run(world);
+
+    self.tick += 1;
+  }
+
+  // Goes back to the first tick that used a wrong guess and simulates up to now again.
+  fn resimulate(&mut self, world: &mut World, simulation: &mut Schedule) {
+    let Some(from) = self.rollback.take() else {
+      return;
+    };
+    let Some(snapshot) = self.snapshots.iter().find(|snapshot| snapshot.tick == from) else {
+      return;
+    };
+    snapshot.restore(world);
+
+    // These ticks were already seen and heard once.
+    world.resource_mut::<TextureManager>().muted = true;
+    let particles = std::mem::take(&mut *world.resource_mut::<Particles>());
+    let camera_controller =
+      std::mem::replace(&mut *world.resource_mut::<CameraController>(), CameraController::new());
+
+    let end = self.tick;
+    self.tick = from;
+    while self.tick < end {
+      self.simulate(world, simulation);
+    }
+
+    world.resource_mut::<TextureManager>().muted = false;
+    world.insert_resource(particles);
+    world.insert_resource(camera_controller);
+  }
+
+  // Sends the checksums of the ticks both inputs are known for, and drops the snapshots no wrong
+  // guess can go back to anymore.
+  fn check_sync(&mut self) {
+    let confirmed = self.tick.min(self.remote_inputs.len() as u32);
+    while self.next_check < confirmed {
+      let tick = self.next_check;
+      if let Some(snapshot) = self.snapshots.iter().find(|snapshot| snapshot.tick == tick) {
+        let value = snapshot.checksum();
+        self.link.send(&Message::Checksum { tick, value });
+        self.local_checksums.insert(tick, value);
+        self.compare(tick);
+      }
+      self.next_check += NET_CHECKSUM_INTERVAL;
+    }
+
+    self.snapshots.retain(|snapshot| snapshot.tick >= confirmed);
+    let keep = |tick: &u32, _: &mut u64| *tick + NET_CHECKSUM_KEEP > confirmed;
+    self.local_checksums.retain(keep);
+    self.remote_checksums.retain(keep);
+  }
+
+  fn compare(&mut self, tick: u32) {
+    let (Some(local), Some(remote)) =
+      (self.local_checksums.get(&tick), self.remote_checksums.get(&tick))
+    else {
+      return;
+    };
+    if local != remote {
+      self.desync = Some(self.desync.map_or(tick, |desync| desync.min(tick)));
+    }
+    self.local_checksums.remove(&tick);
+    self.remote_checksums.remove(&tick);
+  }
+
+  fn send_inputs(&mut self) {
+    let start = (self.peer_ack as usize).min(self.local_inputs.len());
+    let end = (start + NET_MAX_INPUTS_PER_PACKET).min(self.local_inputs.len());
+    let message = Message::Input {
+      ack: self.remote_inputs.len() as u32,
+      start: start as u32,
+      inputs: self.local_inputs[start..end].iter().map(|input| input.to_bits()).collect(),
+    };
+    self.link.send(&message);
+  }
+}
+
+pub fn draw_status(tm: Res<TextureManager>, locale: Res<Locale>, status: Option<Res<NetStatus>>) {
+  let Some(status) = status else {
+    return;
+  };
+
+  let text = if let Some(tick) = status.desync {
+    locale.format("net.desync", &[("tick", &tick)])
+  } else if !status.connected {
+    locale.format("net.waiting", &[("peer", &status.peer)])
+  } else {
+    return;
+  };
+  let rect = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 100.0, 500.0, 50.0);
+  draw_ui_button(&tm, &rect, &text);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::behavior::Personality;
+  use crate::{
+    CatKind,
+    Outcome,
+    PowerUpKind,
+  };
+
+  fn world() -> World {
+    let mut world = World::new();
+    world.spawn().insert_bundle(Player::new(vec2(10.0, 20.0), PlayerId::One, PowerUpKind::SpeedUp));
+    world.spawn().insert_bundle(Tongue::new(vec2(300.0, 40.0), Personality::Sly));
+    world.spawn().insert_bundle(Cat::new(vec2(500.0, 400.0), CatKind::Pouncer));
+    world.insert_resource(RoundOver(None));
+    world.insert_resource(TongueGoal { collected: 0, required: Some(1), total: 1 });
+    world.insert_resource(Score::default());
+    world
+  }
+
+  #[test]
+  fn restored_snapshots_have_the_same_checksum() {
+    let mut world = world();
+    let snapshot = Snapshot::save(&mut world, 7);
+
+    for mut cat in world.query::<&mut Cat>().iter_mut(&mut world) {
+      cat.rect.x += 1.0;
+    }
+    world.insert_resource(RoundOver(Some(Outcome::Lost)));
+    assert_ne!(Snapshot::save(&mut world, 7).checksum(), snapshot.checksum());
+
+    snapshot.restore(&mut world);
+    assert_eq!(Snapshot::save(&mut world, 7).checksum(), snapshot.checksum());
+  }
+}
@@ -0,0 +1,191 @@
+use std::f32::consts::PI;
+
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::camera::{
+  in_views,
+  Views,
+};
+use crate::time::TimeScale;
+use crate::{
+  Player,
+  PowerUpKind,
+};
+
+// Describes how the particles of an effect look and move, start values fade into end values over
+// the lifetime of a particle.
+pub struct Emitter {
+  count: usize,
+  lifetime: (f32, f32),
+  speed: (f32, f32),
+  spread: f32,
+  gravity: f32,
+  drag: f32,
+  size: (f32, f32),
+  color: (Color, Color),
+}
+
+pub const BOUNCE_IMPACT: Emitter = Emitter {
+  count: 12,
+  lifetime: (0.2, 0.45),
+  speed: (120.0, 260.0),
+  spread: PI,
+  gravity: 0.0,
+  drag: 4.0,
+  size: (10.0, 2.0),
+  color: (color_u8!(255, 255, 255, 255), color_u8!(200, 200, 200, 0)),
+};
+
+pub const TONGUE_CAPTURE: Emitter = Emitter {
+  count: 40,
+  lifetime: (0.5, 1.0),
+  speed: (150.0, 380.0),
+  spread: PI / 3.0,
+  gravity: 400.0,
+  drag: 1.5,
+  size: (12.0, 4.0),
+  color: (color_u8!(255, 110, 150, 255), color_u8!(255, 220, 230, 0)),
+};
+
+pub const POWERUP_ACTIVATE: Emitter = Emitter {
+  count: 24,
+  lifetime: (0.3, 0.6),
+  speed: (200.0, 260.0),
+  spread: PI,
+  gravity: 0.0,
+  drag: 3.0,
+  size: (8.0, 8.0),
+  color: (color_u8!(44, 232, 245, 255), color_u8!(0, 153, 219, 0)),
+};
+
+pub const AURA_PULSE: Emitter = Emitter {
+  count: 48,
+  lifetime: (0.3, 0.4),
+  speed: (600.0, 700.0),
+  spread: PI,
+  gravity: 0.0,
+  drag: 2.0,
+  size: (14.0, 6.0),
+  color: (color_u8!(120, 80, 160, 220), color_u8!(40, 40, 50, 0)),
+};
+
+pub const SPEED_TRAIL: Emitter = Emitter {
+  count: 60,
+  lifetime: (0.2, 0.35),
+  speed: (10.0, 40.0),
+  spread: PI / 6.0,
+  gravity: 0.0,
+  drag: 0.0,
+  size: (8.0, 1.0),
+  color: (color_u8!(255, 255, 255, 160), color_u8!(255, 255, 255, 0)),
+};
+
+struct Particle {
+  pos: Vec2,
+  vel: Vec2,
+  age: f32,
+  lifetime: f32,
+  gravity: f32,
+  drag: f32,
+  size: (f32, f32),
+  color: (Color, Color),
+}
+
+#[derive(Default)]
+pub struct Particles(Vec<Particle>);
+
+impl Particles {
+  // Spawns `emitter.count` particles flying along `dir`, or in every direction if it's zero.
+  pub fn burst(&mut self, emitter: &Emitter, pos: Vec2, dir: Vec2) {
+    for _ in 0..emitter.count {
+      self.spawn(emitter, pos, dir);
+    }
+  }
+
+  // Spawns `emitter.count` particles per second, for effects that last while something is active.
+  pub fn stream(&mut self, emitter: &Emitter, pos: Vec2, dir: Vec2, dt: f32) {
+    let count = emitter.count as f32 * dt + rand::gen_range(0.0, 1.0);
+    for _ in 0..count as usize {
+      self.spawn(emitter, pos, dir);
+    }
+  }
+
+  fn spawn(&mut self, emitter: &Emitter, pos: Vec2, dir: Vec2) {
+    let angle = if dir == Vec2::ZERO {
+      rand::gen_range(-PI, PI)
+    } else {
+      dir.y.atan2(dir.x) + rand::gen_range(-emitter.spread, emitter.spread)
+    };
+    let speed = rand::gen_range(emitter.speed.0, emitter.speed.1);
+
+    self.0.push(Particle {
+      pos,
+      vel: vec2(angle.cos(), angle.sin()) * speed,
+      age: 0.0,
+      lifetime: rand::gen_range(emitter.lifetime.0, emitter.lifetime.1),
+      gravity: emitter.gravity,
+      drag: emitter.drag,
+      size: emitter.size,
+      color: emitter.color,
+    });
+  }
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+  Color::new(
+    a.r + (b.r - a.r) * t,
+    a.g + (b.g - a.g) * t,
+    a.b + (b.b - a.b) * t,
+    a.a + (b.a - a.a) * t,
+  )
+}
+
+pub fn update_particles(time_scale: Res<TimeScale>, mut particles: ResMut<Particles>) {
+  let dt = time_scale.delta;
+
+  for particle in &mut particles.0 {
+    particle.age += dt;
+    particle.vel.y += particle.gravity * dt;
+    particle.vel *= 1.0 / (1.0 + particle.drag * dt);
+    particle.pos += particle.vel * dt;
+  }
+  particles.0.retain(|particle| particle.age < particle.lifetime);
+}
+
+pub fn emit_speed_trail(
+  time_scale: Res<TimeScale>,
+  mut particles: ResMut<Particles>,
+  players: Query<&Player>,
+) {
+  for player in &players {
+    if player.powerup_kind == PowerUpKind::SpeedUp && player.powerup_timer > 0.0 {
+      let feet = vec2(player.rect.center().x, player.rect.bottom());
+      particles.stream(&SPEED_TRAIL, feet, -vec2(player.dir_x, 0.0), time_scale.delta);
+    }
+  }
+}
+
+pub fn clear_particles(mut particles: ResMut<Particles>) { particles.0.clear(); }
+
+pub fn draw_particles(views: Res<Views>, particles: Res<Particles>) {
+  in_views(&views, |view| {
+    for particle in &particles.0 {
+      let t = particle.age / particle.lifetime;
+      let size = particle.size.0 + (particle.size.1 - particle.size.0) * t;
+      let rect = view.world_to_screen_rect(Rect::new(
+        particle.pos.x - size / 2.0,
+        particle.pos.y - size / 2.0,
+        size,
+        size,
+      ));
+      draw_rectangle(
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        lerp_color(particle.color.0, particle.color.1, t),
+      );
+    }
+  });
+}
@@ -0,0 +1,54 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// The directory in the user's data directory the game remembers things between runs in.
+const SAVE_DIR: &str = "cgyt";
+
+// The per-user data directory of the platform, as set in the environment.
+fn data_dir() -> Option<PathBuf> {
+  let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+  if cfg!(windows) {
+    var("APPDATA")
+  } else if cfg!(target_os = "macos") {
+    var("HOME").map(|home| home.join("Library").join("Application Support"))
+  } else {
+    var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+  }
+}
+
+fn path(name: &str) -> Option<PathBuf> { Some(data_dir()?.join(SAVE_DIR).join(name)) }
+
+// A missing or unreadable save starts over from the default.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+  path(name)
+    .and_then(|path| fs::read_to_string(path).ok())
+    .and_then(|text| ron::from_str(&text).ok())
+    .unwrap_or_default()
+}
+
+// Not being able to save isn't worth interrupting the game for, the failure only gets logged.
+pub fn store<T: Serialize>(name: &str, value: &T) {
+  let Some(path) = path(name) else {
+    warn!("Failed to save {}: there's no data directory", name);
+    return;
+  };
+  let text = match ron::ser::to_string_pretty(value, Default::default()) {
+    Ok(text) => text,
+    Err(err) => {
+      warn!("Failed to save {}: {}", name, err);
+      return;
+    },
+  };
+  let written = match path.parent() {
+    Some(dir) => fs::create_dir_all(dir).and_then(|()| fs::write(&path, text)),
+    None => fs::write(&path, text),
+  };
+  if let Err(err) = written {
+    warn!("Failed to save {} to {}: {}", name, path.display(), err);
+  }
+}
@@ -0,0 +1,206 @@
+use bevy_ecs::prelude::*;
+use macroquad::audio::play_sound_once;
+use macroquad::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+use crate::locale::{
+  next_language,
+  Locale,
+};
+use crate::{
+  draw_ui_button,
+  flash,
+  save,
+  Flash,
+  GameState,
+  JustPressedBackButton,
+  TextureManager,
+  FONT_SIZE,
+  UI_BG_COLOR,
+  UI_FG_COLOR,
+};
+
+const PREFERENCES_FILE: &str = "preferences.ron";
+// Cycled through in this order by the text size button.
+const TEXT_SCALES: [f32; 4] = [1.0, 1.25, 1.5, 0.75];
+
+const DARKEN_COLOR: Color = color_u8!(0, 0, 0, 100);
+const HIGH_CONTRAST_BG_COLOR: Color = color_u8!(0, 0, 0, 255);
+const HIGH_CONTRAST_FG_COLOR: Color = color_u8!(255, 235, 60, 255);
+const HIGH_CONTRAST_DARKEN_COLOR: Color = color_u8!(0, 0, 0, 190);
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FlashMode {
+  #[default]
+  Full,
+  Reduced,
+  Off,
+}
+
+impl FlashMode {
+  // How bright the flash of menu clicks gets.
+  pub fn strength(&self) -> f32 {
+    match self {
+      FlashMode::Full => 1.0,
+      FlashMode::Reduced => 0.25,
+      FlashMode::Off => 0.0,
+    }
+  }
+
+  fn name(&self) -> &'static str {
+    match self {
+      FlashMode::Full => "settings.flash_full",
+      FlashMode::Reduced => "settings.flash_reduced",
+      FlashMode::Off => "settings.flash_off",
+    }
+  }
+}
+
+// How the UI is drawn, kept in the texture manager so every button can get at it.
+#[derive(Clone, Copy)]
+pub struct Theme {
+  pub bg: Color,
+  pub fg: Color,
+  // Laid over the background behind the menus.
+  pub darken: Color,
+  pub font_size: u16,
+}
+
+// Fields added later need `#[serde(default)]`, so older files keep loading.
+#[derive(Serialize, Deserialize)]
+pub struct Preferences {
+  // `None` follows the system language.
+  #[serde(default)]
+  pub language: Option<String>,
+  #[serde(default)]
+  pub flash: FlashMode,
+  // Shapes above the cats telling the kinds apart, for players who can't tell their fur colors.
+  #[serde(default)]
+  pub cat_badges: bool,
+  #[serde(default)]
+  pub high_contrast: bool,
+  #[serde(default = "default_text_scale")]
+  pub text_scale: f32,
+}
+
+fn default_text_scale() -> f32 { TEXT_SCALES[0] }
+
+impl Default for Preferences {
+  fn default() -> Preferences {
+    Preferences {
+      language: None,
+      flash: FlashMode::default(),
+      cat_badges: false,
+      high_contrast: false,
+      text_scale: default_text_scale(),
+    }
+  }
+}
+
+impl Preferences {
+  pub fn load() -> Preferences {
+    let mut preferences: Preferences = save::load(PREFERENCES_FILE);
+    // The file can be edited by hand, a scale too far off would leave the menus unusable.
+    if !TEXT_SCALES.contains(&preferences.text_scale) {
+      preferences.text_scale = default_text_scale();
+    }
+    preferences
+  }
+
+  pub fn theme(&self) -> Theme {
+    let font_size = (FONT_SIZE as f32 * self.text_scale).round() as u16;
+    if self.high_contrast {
+      Theme {
+        bg: HIGH_CONTRAST_BG_COLOR,
+        fg: HIGH_CONTRAST_FG_COLOR,
+        darken: HIGH_CONTRAST_DARKEN_COLOR,
+        font_size,
+      }
+    } else {
+      Theme { bg: UI_BG_COLOR, fg: UI_FG_COLOR, darken: DARKEN_COLOR, font_size }
+    }
+  }
+}
+
+fn on_off(locale: &Locale, on: bool) -> String {
+  locale.text(if on { "settings.on" } else { "settings.off" })
+}
+
+// The settings are laid out in a column, `index` counts from the top.
+fn settings_button(index: usize) -> Rect {
+  Rect::new(
+    screen_width() / 2.0 - 250.0,
+    screen_height() / 4.0 - 50.0 + 70.0 * index as f32,
+    500.0,
+    50.0,
+  )
+}
+
+pub fn settings_menu(
+  mut tm: ResMut<TextureManager>,
+  mut locale: ResMut<Locale>,
+  mut preferences: ResMut<Preferences>,
+  mut game_state: ResMut<State<GameState>>,
+  mut just_pressed_back_button: ResMut<JustPressedBackButton>,
+  mut flash_timer: ResMut<Flash>,
+) {
+  let mouse_pointer: Vec2 = mouse_position().into();
+  let clicked = is_mouse_button_pressed(MouseButton::Left);
+
+  let texts = [
+    locale.format("settings.language", &[("language", &locale.name())]),
+    locale.format("settings.flash", &[("mode", &locale.text(preferences.flash.name()))]),
+    locale.format("settings.cat_badges", &[("state", &on_off(&locale, preferences.cat_badges))]),
+    locale
+      .format("settings.high_contrast", &[("state", &on_off(&locale, preferences.high_contrast))]),
+    locale.format("settings.text_size", &[("percent", &(preferences.text_scale * 100.0).round())]),
+  ];
+  let mut clicked_setting = None;
+  for (index, text) in texts.iter().enumerate() {
+    let button = settings_button(index);
+    draw_ui_button(&tm, &button, text);
+    if button.contains(mouse_pointer) && clicked {
+      clicked_setting = Some(index);
+    }
+  }
+
+  if let Some(index) = clicked_setting {
+    play_sound_once(tm.ui);
+    match index {
+      0 => {
+        let language = next_language(&locale.code);
+        *locale = Locale::load(&language);
+        preferences.language = Some(language);
+      },
+      1 => {
+        preferences.flash = match preferences.flash {
+          FlashMode::Full => FlashMode::Reduced,
+          FlashMode::Reduced => FlashMode::Off,
+          FlashMode::Off => FlashMode::Full,
+        };
+      },
+      2 => preferences.cat_badges = !preferences.cat_badges,
+      3 => preferences.high_contrast = !preferences.high_contrast,
+      _ => {
+        let index = TEXT_SCALES.iter().position(|scale| *scale == preferences.text_scale);
+        preferences.text_scale = TEXT_SCALES[(index.unwrap_or(0) + 1) % TEXT_SCALES.len()];
+      },
+    }
+    tm.theme = preferences.theme();
+    save::store(PREFERENCES_FILE, &*preferences);
+  }
+
+  let back_button = Rect::new(screen_width() / 2.0 - 250.0, screen_height() - 100.0, 500.0, 50.0);
+  draw_ui_button(&tm, &back_button, &locale.text("menu.back"));
+
+  if back_button.contains(mouse_pointer) && clicked {
+    play_sound_once(tm.ui);
+    flash(&mut flash_timer);
+    let _ = game_state.overwrite_set(GameState::MainMenu);
+    just_pressed_back_button.0 = true;
+    just_pressed_back_button.1 = 0.1;
+  }
+}
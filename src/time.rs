@@ -0,0 +1,132 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::console::{
+  arg,
+  no_completion,
+  Command,
+};
+use crate::Cheated;
+
+const TIME_SCALE_MIN: f32 = 0.05;
+const TIME_SCALE_MAX: f32 = 10.0;
+// The tick a single step covers, at a time scale of one.
+const STEP_TIME: f32 = 1.0 / 60.0;
+// Real seconds the end of a round is played in slow motion for.
+const SLOW_MOTION_TIME: f32 = 1.2;
+const SLOW_MOTION_SCALE: f32 = 0.2;
+
+const PAUSE_KEY: KeyCode = KeyCode::F5;
+const STEP_KEY: KeyCode = KeyCode::F6;
+// Pressing one of these again goes back to normal speed.
+const SCALE_KEYS: [(KeyCode, f32); 3] =
+  [(KeyCode::F7, 0.25), (KeyCode::F8, 0.5), (KeyCode::F9, 2.0)];
+
+// How fast the game runs offline, online every tick is the same length for both players.
+pub struct TimeScale {
+  scale: f32,
+  paused: bool,
+  // Set to run a single tick while paused.
+  step: bool,
+  // Real seconds of slow motion left, `None` if it isn't playing.
+  slow_motion: Option<f32>,
+  // The scaled time of this frame, for everything outside the simulation that moves.
+  pub delta: f32,
+}
+
+impl Default for TimeScale {
+  fn default() -> TimeScale {
+    TimeScale { scale: 1.0, paused: false, step: false, slow_motion: None, delta: 0.0 }
+  }
+}
+
+impl TimeScale {
+  pub fn paused(&self) -> bool { self.paused }
+
+  pub fn toggle_pause(&mut self) { self.paused = !self.paused; }
+
+  pub fn step(&mut self) {
+    self.paused = true;
+    self.step = true;
+  }
+
+  fn current(&self) -> f32 {
+    match self.slow_motion {
+      Some(left) if left > 0.0 => self.scale * SLOW_MOTION_SCALE,
+      _ => self.scale,
+    }
+  }
+
+  // Moves on to the next frame, returns the time the simulation covers or `None` while paused.
+  pub fn advance(&mut self) -> Option<f32> {
+    let frame_time = get_frame_time();
+    if let Some(left) = &mut self.slow_motion {
+      *left -= frame_time;
+    }
+
+    let dt = if self.step {
+      Some(STEP_TIME * self.current())
+    } else if self.paused {
+      None
+    } else {
+      Some(frame_time * self.current())
+    };
+    self.step = false;
+    self.delta = dt.unwrap_or(0.0);
+    dt
+  }
+
+  // Starts the slow motion of a decided round, true until it has played out.
+  pub fn slow_motion(&mut self) -> bool {
+    match self.slow_motion {
+      None => {
+        self.slow_motion = Some(SLOW_MOTION_TIME);
+        true
+      },
+      Some(left) if left > 0.0 => true,
+      Some(_) => {
+        self.slow_motion = None;
+        false
+      },
+    }
+  }
+}
+
+// Pausing, stepping or changing the speed counts as cheating, like the `timescale` command, a run
+// played slower would otherwise record a normal time.
+pub fn control_time(mut time_scale: ResMut<TimeScale>, mut cheated: ResMut<Cheated>) {
+  if is_key_pressed(PAUSE_KEY) {
+    time_scale.toggle_pause();
+    cheated.0 = true;
+  }
+  if is_key_pressed(STEP_KEY) {
+    time_scale.step();
+    cheated.0 = true;
+  }
+  for (key, scale) in SCALE_KEYS {
+    if is_key_pressed(key) {
+      time_scale.scale = if time_scale.scale == scale { 1.0 } else { scale };
+      cheated.0 = true;
+    }
+  }
+}
+
+// A round left while its end was still playing in slow motion mustn't start the next one with it.
+pub fn reset_slow_motion(mut time_scale: ResMut<TimeScale>) { time_scale.slow_motion = None; }
+
+pub const COMMANDS: &[Command] = &[Command {
+  name: "timescale",
+  usage: "timescale <scale>",
+  complete: no_completion,
+  run: timescale,
+}];
+
+fn timescale(world: &mut World, args: &[&str]) -> Result<String, String> {
+  let scale: f32 = arg(args, 0)?;
+  if !(TIME_SCALE_MIN..=TIME_SCALE_MAX).contains(&scale) {
+    return Err(format!("The scale goes from {} to {}", TIME_SCALE_MIN, TIME_SCALE_MAX));
+  }
+
+  world.resource_mut::<TimeScale>().scale = scale;
+  Ok(format!("Time runs at {}x", scale))
+}
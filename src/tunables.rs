@@ -0,0 +1,426 @@
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+use std::path::{
+  Path,
+  PathBuf,
+};
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+use std::time::SystemTime;
+
+use std::ops::RangeInclusive;
+
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+use crate::assets;
+use crate::console::{
+  arg,
+  complete_from,
+  Command,
+};
+use crate::difficulty::Difficulty;
+
+const TUNABLES_FILE: &str = "tunables.ron";
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+const POLL_INTERVAL: f32 = 0.5;
+
+// The numbers the game is balanced with, read from `res/tunables.ron`. Anything missing from the
+// file keeps the value below.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tunables {
+  pub player_speed: f32,
+  pub player_speed_up_time: f32,
+  pub player_speed_up_mul: f32,
+  pub player_no_bounce_time: f32,
+  pub player_bouncier_time: f32,
+  pub player_bouncier_mul: f32,
+  pub player_powerup_cooldown: f32,
+  pub camera_bounce_trauma: f32,
+  // How much of a bounce plays out per second, for players and cats alike.
+  pub bounce_speed: f32,
+
+  pub tongue_speed: f32,
+  pub tongue_max_dest: f32,
+  // How far ahead a fleeing tongue checks for walls and obstacles.
+  pub tongue_lookahead: f32,
+  pub tongue_wall_range: f32,
+  pub tongue_juke_time: f32,
+  pub tongue_player_threat: f32,
+  // The personalities of the tongues, the levels pick one per tongue.
+  pub tongue_skittish_alert_range: f32,
+  pub tongue_skittish_speed_mul: f32,
+  pub tongue_skittish_juke_rate: f32,
+  pub tongue_skittish_caution: f32,
+  pub tongue_lazy_alert_range: f32,
+  pub tongue_lazy_speed_mul: f32,
+  pub tongue_lazy_juke_rate: f32,
+  pub tongue_lazy_caution: f32,
+  pub tongue_sly_alert_range: f32,
+  pub tongue_sly_speed_mul: f32,
+  pub tongue_sly_juke_rate: f32,
+  pub tongue_sly_caution: f32,
+
+  pub cat_speed: f32,
+  pub cat_defender_proximity: f32,
+  pub cat_slowing_proximity: f32,
+  pub cat_max_dest: f32,
+  pub cat_attacker_bounce: f32,
+  pub cat_defender_bounce: f32,
+  pub cat_slowing_bounce: f32,
+  pub cat_pouncer_bounce: f32,
+  pub cat_sneak_bounce: f32,
+  pub cat_herder_bounce: f32,
+  pub cat_splitter_bounce: f32,
+  pub cat_kitten_bounce: f32,
+  // How scary every kind of cat is to the tongue.
+  pub cat_attacker_threat: f32,
+  pub cat_defender_threat: f32,
+  pub cat_slowing_threat: f32,
+  pub cat_pouncer_threat: f32,
+  pub cat_sneak_threat: f32,
+  pub cat_herder_threat: f32,
+  pub cat_splitter_threat: f32,
+  pub cat_kitten_threat: f32,
+  pub cat_slowing_mul: f32,
+  pub cat_flee_range: f32,
+  pub cat_guard_range: f32,
+  pub cat_guard_distance: f32,
+  pub cat_patrol_radius: f32,
+  pub cat_pouncer_range: f32,
+  pub cat_pouncer_windup: f32,
+  pub cat_pouncer_leap_time: f32,
+  pub cat_pouncer_leap_mul: f32,
+  pub cat_pouncer_rest: f32,
+  pub cat_sneak_reveal_range: f32,
+  pub cat_sneak_alpha: f32,
+  pub cat_sneak_fade_speed: f32,
+  pub cat_herder_orbit: f32,
+  // How far ahead around the tongue a herder aims, in radians.
+  pub cat_herder_orbit_step: f32,
+  // Angle between the two kittens a splitter breaks into, in radians.
+  pub cat_kitten_spread: f32,
+
+  pub obstacle_maneki_proximity: f32,
+  // Speed of the cats around a maneki.
+  pub obstacle_maneki_speed_mul: f32,
+}
+
+impl Default for Tunables {
+  fn default() -> Tunables {
+    Tunables {
+      player_speed: 160.0,
+      player_speed_up_time: 2.0,
+      player_speed_up_mul: 1.6,
+      player_no_bounce_time: 6.0,
+      player_bouncier_time: 10.0,
+      player_bouncier_mul: 3.0,
+      player_powerup_cooldown: 6.0,
+      camera_bounce_trauma: 0.45,
+      bounce_speed: 3.0,
+
+      tongue_speed: 120.0,
+      tongue_max_dest: 120.0,
+      tongue_lookahead: 120.0,
+      tongue_wall_range: 150.0,
+      tongue_juke_time: 0.35,
+      tongue_player_threat: 0.5,
+      tongue_skittish_alert_range: 450.0,
+      tongue_skittish_speed_mul: 1.1,
+      tongue_skittish_juke_rate: 0.2,
+      tongue_skittish_caution: 1.0,
+      tongue_lazy_alert_range: 200.0,
+      tongue_lazy_speed_mul: 0.8,
+      tongue_lazy_juke_rate: 0.0,
+      tongue_lazy_caution: 0.5,
+      tongue_sly_alert_range: 300.0,
+      tongue_sly_speed_mul: 1.0,
+      tongue_sly_juke_rate: 1.0,
+      tongue_sly_caution: 1.5,
+
+      cat_speed: 140.0,
+      cat_defender_proximity: 152.0,
+      cat_slowing_proximity: 224.0,
+      cat_max_dest: 140.0,
+      cat_attacker_bounce: 75.0,
+      cat_defender_bounce: 152.0,
+      cat_slowing_bounce: 35.0,
+      cat_pouncer_bounce: 90.0,
+      cat_sneak_bounce: 60.0,
+      cat_herder_bounce: 110.0,
+      cat_splitter_bounce: 75.0,
+      cat_kitten_bounce: 120.0,
+      cat_attacker_threat: 1.0,
+      cat_defender_threat: 0.6,
+      cat_slowing_threat: 0.5,
+      cat_pouncer_threat: 1.5,
+      cat_sneak_threat: 1.2,
+      cat_herder_threat: 0.8,
+      cat_splitter_threat: 1.0,
+      cat_kitten_threat: 0.8,
+      cat_slowing_mul: 0.75,
+      cat_flee_range: 200.0,
+      cat_guard_range: 250.0,
+      cat_guard_distance: 90.0,
+      cat_patrol_radius: 150.0,
+      cat_pouncer_range: 280.0,
+      cat_pouncer_windup: 0.6,
+      cat_pouncer_leap_time: 0.4,
+      cat_pouncer_leap_mul: 4.0,
+      cat_pouncer_rest: 1.5,
+      cat_sneak_reveal_range: 180.0,
+      cat_sneak_alpha: 0.15,
+      cat_sneak_fade_speed: 4.0,
+      cat_herder_orbit: 170.0,
+      cat_herder_orbit_step: 0.6,
+      cat_kitten_spread: 1.2,
+
+      obstacle_maneki_proximity: 192.0,
+      obstacle_maneki_speed_mul: 1.5,
+    }
+  }
+}
+
+// Every field with the range it has to be in, `validate` puts fields outside of it back to the
+// default and the console gets at them by name.
+macro_rules! fields {
+  ($($field:ident: $min:expr, $max:expr;)*) => {
+    const NAMES: &[&str] = &[$(stringify!($field)),*];
+
+    fn validate(&mut self) {
+      let default = Tunables::default();
+      $(
+        if !($min..=$max).contains(&self.$field) {
+          warn!(
+            "`{}` in {} is {}, it has to be from {} to {}",
+            stringify!($field),
+            TUNABLES_FILE,
+            self.$field,
+            $min,
+            $max
+          );
+          self.$field = default.$field;
+        }
+      )*
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<(&mut f32, RangeInclusive<f32>)> {
+      match name {
+        $(stringify!($field) => Some((&mut self.$field, $min..=$max)),)*
+        _ => None,
+      }
+    }
+  };
+}
+
+impl Tunables {
+  fn parse(bytes: &[u8]) -> Result<Tunables, String> {
+    let text = std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+    let mut tunables: Tunables = ron::from_str(text).map_err(|err| err.to_string())?;
+    tunables.validate();
+    Ok(tunables)
+  }
+
+  // A broken file shouldn't keep the game from starting, it's played with the defaults instead.
+  pub fn load() -> Tunables {
+    assets::load_with(TUNABLES_FILE, Tunables::parse).unwrap_or_else(|err| {
+      warn!("Failed to load {}: {}", TUNABLES_FILE, err);
+      Tunables::default()
+    })
+  }
+
+  fields! {
+    player_speed: 1.0, 2000.0;
+    player_speed_up_time: 0.0, 60.0;
+    player_speed_up_mul: 1.0, 10.0;
+    player_no_bounce_time: 0.0, 60.0;
+    player_bouncier_time: 0.0, 60.0;
+    player_bouncier_mul: 1.0, 10.0;
+    player_powerup_cooldown: 0.0, 60.0;
+    camera_bounce_trauma: 0.0, 1.0;
+    bounce_speed: 0.1, 100.0;
+    tongue_speed: 0.0, 2000.0;
+    tongue_max_dest: 1.0, 1000.0;
+    tongue_lookahead: 1.0, 1000.0;
+    tongue_wall_range: 1.0, 1000.0;
+    tongue_juke_time: 0.0, 10.0;
+    tongue_player_threat: 0.0, 10.0;
+    tongue_skittish_alert_range: 0.0, 2000.0;
+    tongue_skittish_speed_mul: 0.0, 10.0;
+    tongue_skittish_juke_rate: 0.0, 10.0;
+    tongue_skittish_caution: 0.0, 10.0;
+    tongue_lazy_alert_range: 0.0, 2000.0;
+    tongue_lazy_speed_mul: 0.0, 10.0;
+    tongue_lazy_juke_rate: 0.0, 10.0;
+    tongue_lazy_caution: 0.0, 10.0;
+    tongue_sly_alert_range: 0.0, 2000.0;
+    tongue_sly_speed_mul: 0.0, 10.0;
+    tongue_sly_juke_rate: 0.0, 10.0;
+    tongue_sly_caution: 0.0, 10.0;
+    cat_speed: 0.0, 2000.0;
+    cat_defender_proximity: 0.0, 1000.0;
+    cat_slowing_proximity: 0.0, 1000.0;
+    cat_max_dest: 1.0, 1000.0;
+    cat_attacker_bounce: 0.0, 1000.0;
+    cat_defender_bounce: 0.0, 1000.0;
+    cat_slowing_bounce: 0.0, 1000.0;
+    cat_pouncer_bounce: 0.0, 1000.0;
+    cat_sneak_bounce: 0.0, 1000.0;
+    cat_herder_bounce: 0.0, 1000.0;
+    cat_splitter_bounce: 0.0, 1000.0;
+    cat_kitten_bounce: 0.0, 1000.0;
+    cat_attacker_threat: 0.0, 10.0;
+    cat_defender_threat: 0.0, 10.0;
+    cat_slowing_threat: 0.0, 10.0;
+    cat_pouncer_threat: 0.0, 10.0;
+    cat_sneak_threat: 0.0, 10.0;
+    cat_herder_threat: 0.0, 10.0;
+    cat_splitter_threat: 0.0, 10.0;
+    cat_kitten_threat: 0.0, 10.0;
+    cat_slowing_mul: 0.0, 1.0;
+    cat_flee_range: 0.0, 1000.0;
+    cat_guard_range: 0.0, 1000.0;
+    cat_guard_distance: 0.0, 1000.0;
+    cat_patrol_radius: 0.0, 1000.0;
+    cat_pouncer_range: 0.0, 1000.0;
+    cat_pouncer_windup: 0.0, 10.0;
+    cat_pouncer_leap_time: 0.0, 10.0;
+    cat_pouncer_leap_mul: 1.0, 20.0;
+    cat_pouncer_rest: 0.0, 10.0;
+    cat_sneak_reveal_range: 0.0, 1000.0;
+    cat_sneak_alpha: 0.0, 1.0;
+    cat_sneak_fade_speed: 0.0, 100.0;
+    cat_herder_orbit: 0.0, 1000.0;
+    cat_herder_orbit_step: 0.0, std::f32::consts::PI;
+    cat_kitten_spread: 0.0, std::f32::consts::TAU;
+    obstacle_maneki_proximity: 0.0, 1000.0;
+    obstacle_maneki_speed_mul: 0.0, 10.0;
+  }
+}
+
+// The tunables as they are in the file, `Tunables` is what's played with after the difficulty
+// scaled them.
+pub struct BaseTunables(pub Tunables);
+
+// Runs in the simulation, so both players of an online match play with the host's difficulty from
+// the first tick on.
+pub fn scale_tunables(
+  base: Res<BaseTunables>,
+  difficulty: Res<Difficulty>,
+  mut tunables: ResMut<Tunables>,
+) {
+  if !base.is_changed() && !difficulty.is_changed() {
+    return;
+  }
+
+  let scaling = difficulty.scaling();
+  let mut scaled = base.0.clone();
+  scaled.cat_speed *= scaling.cat_speed;
+  scaled.cat_defender_proximity *= scaling.proximity;
+  scaled.cat_slowing_proximity *= scaling.proximity;
+  // Slowing down more than all the way would make the player walk backwards.
+  scaled.cat_slowing_mul = (1.0 - (1.0 - scaled.cat_slowing_mul) * scaling.slowing).max(0.0);
+  for bounce in [
+    &mut scaled.cat_attacker_bounce,
+    &mut scaled.cat_defender_bounce,
+    &mut scaled.cat_slowing_bounce,
+    &mut scaled.cat_pouncer_bounce,
+    &mut scaled.cat_sneak_bounce,
+    &mut scaled.cat_herder_bounce,
+    &mut scaled.cat_splitter_bounce,
+    &mut scaled.cat_kitten_bounce,
+  ] {
+    *bounce *= scaling.bounce;
+  }
+  scaled.player_powerup_cooldown *= scaling.powerup_cooldown;
+  *tunables = scaled;
+}
+
+pub const COMMANDS: &[Command] =
+  &[Command { name: "set", usage: "set <tunable> <value>", complete: complete_set, run: set }];
+
+fn complete_set(_: &World, index: usize) -> Vec<String> { complete_from(&[Tunables::NAMES], index) }
+
+// Changes the value from the file, the difficulty still scales it and reloading the file resets it.
+fn set(world: &mut World, args: &[&str]) -> Result<String, String> {
+  let name: String = arg(args, 0)?;
+  let value: f32 = arg(args, 1)?;
+  let mut base = world.resource_mut::<BaseTunables>();
+  let Some((field, range)) = base.0.field_mut(&name) else {
+    return Err(format!("There's no tunable `{}`", name));
+  };
+  if !range.contains(&value) {
+    return Err(format!("`{}` has to be from {} to {}", name, range.start(), range.end()));
+  }
+  *field = value;
+  Ok(format!("{} is {} now", name, value))
+}
+
+// Debug builds pick up changes to the file while playing. The modded copy is watched when there is
+// one, the one in the repository otherwise, so a plain `cargo run` can be tuned too.
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub struct TunablesWatcher {
+  path: PathBuf,
+  timer: f32,
+  modified: Option<SystemTime>,
+}
+
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+impl TunablesWatcher {
+  pub fn new() -> TunablesWatcher {
+    let path = assets::override_dir()
+      .map(|dir| dir.join(TUNABLES_FILE))
+      .filter(|path| path.is_file())
+      .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("res").join(TUNABLES_FILE));
+    let mut watcher = TunablesWatcher { path, timer: POLL_INTERVAL, modified: None };
+    watcher.modified = watcher.modified();
+    watcher
+  }
+
+  fn modified(&self) -> Option<SystemTime> { std::fs::metadata(&self.path).ok()?.modified().ok() }
+}
+
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub fn watch_tunables(mut watcher: ResMut<TunablesWatcher>, mut base: ResMut<BaseTunables>) {
+  watcher.timer -= get_frame_time();
+  if watcher.timer > 0.0 {
+    return;
+  }
+  watcher.timer = POLL_INTERVAL;
+
+  let modified = watcher.modified();
+  if modified == watcher.modified {
+    return;
+  }
+  watcher.modified = modified;
+
+  // A half-written or broken file keeps the values from before.
+  let read = std::fs::read(&watcher.path).map_err(|err| err.to_string());
+  match read.and_then(|bytes| Tunables::parse(&bytes)) {
+    Ok(tunables) => {
+      base.0 = tunables;
+      info!("Reloaded {}", TUNABLES_FILE);
+    },
+    Err(err) => warn!("Failed to reload {}: {}", TUNABLES_FILE, err),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The file is there to be tweaked, but the game ships with the same numbers in both places.
+  #[test]
+  fn file_matches_the_defaults() {
+    let text = include_str!("../res/tunables.ron");
+    let file: Tunables = ron::from_str(text).unwrap();
+    assert_eq!(file, Tunables::default());
+    // Left out of the file the default would still be played with, but nobody would find it to tweak.
+    for name in Tunables::NAMES {
+      assert!(text.contains(&format!("{}:", name)), "`{}` is missing from the file", name);
+    }
+  }
+}
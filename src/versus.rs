@@ -0,0 +1,230 @@
+use bevy_ecs::prelude::*;
+use macroquad::prelude::*;
+
+use crate::animation::{
+  Animator,
+  Clip,
+};
+use crate::hud::draw_badge;
+use crate::input::{
+  Inputs,
+  PlayerId,
+};
+use crate::locale::Locale;
+use crate::particles::{
+  Particles,
+  AURA_PULSE,
+};
+use crate::settings::Preferences;
+use crate::tunables::Tunables;
+use crate::{
+  draw_ui_button,
+  Cat,
+  CatKind,
+  DeltaTime,
+  Obstacle,
+  Outcome,
+  Pathfinder,
+  PlayMode,
+  Player,
+  TextureManager,
+  LEVEL_WIDTH,
+  UI_BUTTON_OUTLINE,
+};
+
+// The match ends once either side has won the majority of this many rounds.
+const VERSUS_BEST_OF: usize = 5;
+// The driven cat starts above the level, away from every tongue.
+const VERSUS_CAT_Y: f32 = -250.0;
+const VERSUS_DASH_MUL: f32 = 3.5;
+const VERSUS_DASH_TIME: f32 = 0.25;
+const VERSUS_DASH_COOLDOWN: f32 = 2.0;
+const VERSUS_PULSE_RANGE: f32 = 260.0;
+const VERSUS_PULSE_STUN: f32 = 0.8;
+const VERSUS_PULSE_COOLDOWN: f32 = 5.0;
+
+// A cat controlled by the second player instead of a `Brain`.
+#[derive(Component, Clone)]
+pub struct Driven {
+  facing: Vec2,
+  dash_timer: f32,
+  cooldown: f32,
+}
+
+// The kind of cat the second player picked in the level select.
+pub struct VersusCat(pub CatKind);
+
+#[derive(Clone, Default, Hash)]
+pub struct Score {
+  skull: usize,
+  cat: usize,
+}
+
+impl Score {
+  pub fn record(&mut self, outcome: Outcome) {
+    match outcome {
+      Outcome::Won => self.skull += 1,
+      Outcome::Lost => self.cat += 1,
+    }
+  }
+
+  pub fn decided(&self) -> bool { self.skull.max(self.cat) > VERSUS_BEST_OF / 2 }
+}
+
+pub fn spawn_versus_cat(mut commands: Commands, mode: Res<PlayMode>, versus_cat: Res<VersusCat>) {
+  if *mode != PlayMode::Versus {
+    return;
+  }
+
+  let (mut cat, pathfinder, animator, _) = Cat::new(Vec2::ZERO, versus_cat.0);
+  cat.rect.x = (LEVEL_WIDTH - cat.rect.w) / 2.0;
+  cat.rect.y = VERSUS_CAT_Y;
+  commands.spawn_bundle((
+    cat,
+    pathfinder,
+    animator,
+    Driven { facing: vec2(0.0, 1.0), dash_timer: 0.0, cooldown: 0.0 },
+  ));
+}
+
+// A finished match stays around for the level select to show, the next one starts from zero.
+pub fn start_round(mut score: ResMut<Score>) {
+  if score.decided() {
+    *score = Score::default();
+  }
+}
+
+pub fn drive_cat(
+  dt: Res<DeltaTime>,
+  tunables: Res<Tunables>,
+  inputs: Res<Inputs>,
+  mut cats: Query<(&mut Cat, &mut Pathfinder, &mut Animator, &mut Driven)>,
+  mut players: Query<&mut Player>,
+  obstacles: Query<&Obstacle>,
+  mut particles: ResMut<Particles>,
+) {
+  let dt = dt.0;
+  let input = inputs.get(PlayerId::Two);
+
+  for (mut cat, mut pathfinder, mut animator, mut driven) in &mut cats {
+    driven.dash_timer -= dt;
+    driven.cooldown -= dt;
+
+    let steer = vec2(input.x as f32, input.y as f32).normalize_or_zero();
+    if steer != Vec2::ZERO {
+      driven.facing = steer;
+    }
+
+    if input.powerup && driven.cooldown <= 0.0 {
+      match cat.kind {
+        // Stuns every player caught in the pulse.
+        CatKind::Slowing => {
+          for mut player in &mut players {
+            if player.rect.center().distance(cat.rect.center()) < VERSUS_PULSE_RANGE {
+              player.stun_timer = VERSUS_PULSE_STUN;
+            }
+          }
+          particles.burst(&AURA_PULSE, cat.rect.center(), Vec2::ZERO);
+          driven.cooldown = VERSUS_PULSE_COOLDOWN;
+        },
+        _ => {
+          driven.dash_timer = VERSUS_DASH_TIME;
+          driven.cooldown = VERSUS_DASH_COOLDOWN;
+        },
+      }
+    }
+
+    let dashing = driven.dash_timer > 0.0;
+    let dir = if dashing { driven.facing } else { steer };
+    let bouncing = cat.bounce_percentage.is_some();
+    animator.play(if bouncing {
+      Clip::Bounced
+    } else if dashing {
+      Clip::Alert
+    } else if dir != Vec2::ZERO {
+      Clip::Walk
+    } else {
+      Clip::Idle
+    });
+
+    if dir == Vec2::ZERO {
+      pathfinder.dest = None;
+      continue;
+    }
+    if dir.x != 0.0 {
+      cat.dir_x = dir.x;
+    }
+
+    let speed = tunables.cat_speed * cat.speed_mul * if dashing { VERSUS_DASH_MUL } else { 1.0 };
+    let dest = cat.rect.point() + dir * tunables.cat_max_dest;
+    pathfinder.update_pos(&mut cat.rect, speed, dest, &obstacles, bouncing, dt);
+  }
+}
+
+fn score_text(locale: &Locale, score: &Score) -> String {
+  locale.format("versus.score", &[("skull", &score.skull), ("cat", &score.cat)])
+}
+
+pub fn draw_score(
+  tm: Res<TextureManager>,
+  locale: Res<Locale>,
+  mode: Res<PlayMode>,
+  score: Res<Score>,
+) {
+  if *mode != PlayMode::Versus {
+    return;
+  }
+
+  let rect = Rect::new(screen_width() / 2.0 - 150.0, 10.0, 300.0, 50.0);
+  draw_ui_button(&tm, &rect, &score_text(&locale, &score));
+}
+
+// The cat picker and the result of the last match, next to the level select.
+pub fn versus_select(
+  tm: Res<TextureManager>,
+  locale: Res<Locale>,
+  preferences: Res<Preferences>,
+  mode: Res<PlayMode>,
+  mut versus_cat: ResMut<VersusCat>,
+  mut score: ResMut<Score>,
+) {
+  if *mode != PlayMode::Versus {
+    return;
+  }
+
+  // Leaving a match before it's decided forfeits it.
+  if !score.decided() {
+    *score = Score::default();
+  } else {
+    let rect = Rect::new(screen_width() / 2.0 - 250.0, 20.0, 500.0, 50.0);
+    let winner = if score.skull > score.cat { "versus.skull_wins" } else { "versus.cat_wins" };
+    let text = locale.format(winner, &[("score", &score_text(&locale, &score))]);
+    draw_ui_button(&tm, &rect, &text);
+  }
+
+  let button = Rect::new(screen_width() - 64.0, (screen_height() - 64.0) / 2.0, 64.0, 64.0);
+  draw_rectangle(button.x, button.y, button.w, button.h, tm.theme.fg);
+  tm.atlas.draw(
+    match versus_cat.0 {
+      CatKind::Slowing => "cat_black",
+      _ => "cat_grey",
+    },
+    button.x + UI_BUTTON_OUTLINE,
+    button.y + UI_BUTTON_OUTLINE,
+    WHITE,
+    DrawTextureParams {
+      dest_size: Some(button.size() - UI_BUTTON_OUTLINE * 2.0),
+      ..Default::default()
+    },
+  );
+  if preferences.cat_badges {
+    draw_badge(&versus_cat.0, button.point() + UI_BUTTON_OUTLINE * 3.0, 1.0);
+  }
+
+  if button.contains(mouse_position().into()) && is_mouse_button_pressed(MouseButton::Left) {
+    versus_cat.0 = match versus_cat.0 {
+      CatKind::Attacker => CatKind::Slowing,
+      _ => CatKind::Attacker,
+    };
+  }
+}